@@ -1,18 +1,25 @@
 pub mod programmingbitcoin;
 
 extern crate hmac;
+extern crate ripemd160;
 extern crate rug;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_cbor;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 extern crate sha2;
 
 use rug::Integer;
 use rug::integer::Order;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use programmingbitcoin::ecdsa::*;
 use programmingbitcoin::serialization::*;
 
 fn main() {
-    let curve = Rc::new(CryptographicCurve::new_secp256k1());
+    let curve = Arc::new(CryptographicCurve::new_secp256k1());
     let private_key = PrivateKey::new(curve.make_element(Integer::from(5000)), &curve);
     let encoded = private_key.public_key.as_sec();
     println!("{:02x?} - {}", encoded, encoded.len());
@@ -3,6 +3,8 @@ use std::iter;
 use rug::Integer;
 use rug::integer::Order;
 
+use programmingbitcoin::messagedigest::*;
+
 static BASE58_ALPHABET : &'static [u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
 
 pub fn base58_encode(val: Vec<u8>) -> Vec<u8> {
@@ -32,6 +34,62 @@ pub fn base58_encode(val: Vec<u8>) -> Vec<u8> {
     final_value
 }
 
+/// Decodes a Base58 string back into bytes.
+pub fn base58_decode(val: &[u8]) -> Result<Vec<u8>, String> {
+    let leading_ones_count = val.iter().take_while(|&&ch| ch == b'1').count();
+
+    let mut num = Integer::from(0);
+    for ch in val {
+        let digit = BASE58_ALPHABET.iter().position(|a| a == ch)
+            .ok_or_else(|| format!("Invalid Base58 character {:?}", *ch as char))?;
+        num = num * 58 + digit as u32;
+    }
+
+    let mut result = vec![0u8; leading_ones_count];
+    if num > 0 {
+        result.extend(num.to_digits::<u8>(Order::MsfBe));
+    }
+    Ok(result)
+}
+
+/// Encodes bytes in Base58Check, appending the first four bytes of their
+/// Hash256 as a checksum before encoding.
+pub fn base58check_encode(val: Vec<u8>) -> Vec<u8> {
+    let checksum = hash256(&val);
+    let mut data = val;
+    data.extend_from_slice(&checksum.as_bytes()[..4]);
+    base58_encode(data)
+}
+
+/// Decodes a Base58Check string, verifying and removing its checksum.
+pub fn base58check_decode(val: &[u8]) -> Result<Vec<u8>, String> {
+    let mut data = base58_decode(val)?;
+    if data.len() < 4 {
+        return Err("Base58Check data is too short".to_string());
+    }
+
+    let checksum = data.split_off(data.len() - 4);
+    if hash256(&data).as_bytes()[..4] != checksum[..] {
+        return Err("Base58Check checksum mismatch".to_string());
+    }
+    Ok(data)
+}
+
+#[test]
+fn test_base58check() {
+    let data = Integer::from_str_radix("00f54a5851e9372b87810a8e60cdd2e7cfd80b6e31", 16).unwrap();
+    let mut bytes = vec![0x00];
+    bytes.extend(data.to_digits::<u8>(Order::MsfBe));
+
+    let encoded = base58check_encode(bytes.clone());
+    assert_eq!(encoded, &b"1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs"[..]);
+    assert_eq!(base58check_decode(&encoded), Ok(bytes.clone()));
+    assert_eq!(base58_decode(&base58_encode(bytes.clone())), Ok(bytes));
+
+    assert!(base58check_decode(b"1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAt").is_err());
+    assert!(base58_decode(b"0OIl").is_err());
+}
+
 #[test]
 fn test_base58_encoding() {
     let values = vec![
@@ -0,0 +1,82 @@
+//! Base64 encoding and decoding, as used by PEM files
+static BASE64_ALPHABET : &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes as padded Base64.
+pub fn base64_encode(val: &[u8]) -> String {
+    let mut result = String::with_capacity(val.len().div_ceil(3) * 4);
+    for chunk in val.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = (group[0] as u32) << 16 | (group[1] as u32) << 8 | group[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (bits >> (18 - 6 * i)) & 0x3f;
+                result.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// Decodes padded Base64, rejecting missing padding, stray characters and
+/// non-zero trailing bits.
+pub fn base64_decode(val: &str) -> Result<Vec<u8>, String> {
+    let val = val.as_bytes();
+    if !val.len().is_multiple_of(4) {
+        return Err("Base64 length must be a multiple of four".to_string());
+    }
+
+    let mut result = Vec::with_capacity(val.len() / 4 * 3);
+    for (n, chunk) in val.chunks(4).enumerate() {
+        let last = n == val.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&ch| ch == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return Err("Invalid Base64 padding".to_string());
+        }
+
+        let mut bits = 0u32;
+        for &ch in &chunk[..4 - padding] {
+            let digit = BASE64_ALPHABET.iter().position(|&a| a == ch)
+                .ok_or_else(|| format!("Invalid Base64 character {:?}", ch as char))?;
+            bits = bits << 6 | digit as u32;
+        }
+        bits <<= 6 * padding;
+        if bits & ((1 << (8 * padding)) - 1) != 0 {
+            return Err("Base64 has non-zero trailing bits".to_string());
+        }
+
+        let bytes = [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8];
+        result.extend_from_slice(&bytes[..3 - padding]);
+    }
+    Ok(result)
+}
+
+#[test]
+fn test_base64() {
+    // Test vectors from RFC 4648
+    let vectors = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy")
+    ];
+    for &(plain, encoded) in &vectors {
+        assert_eq!(base64_encode(plain.as_bytes()), encoded);
+        assert_eq!(base64_decode(encoded), Ok(plain.as_bytes().to_vec()));
+    }
+
+    let bytes: Vec<u8> = (0..=255).collect();
+    assert_eq!(base64_decode(&base64_encode(&bytes)), Ok(bytes));
+
+    assert!(base64_decode("Zm9").is_err());
+    assert!(base64_decode("Zg=a").is_err());
+    assert!(base64_decode("Zg==Zm9v").is_err());
+    assert!(base64_decode("Zh==").is_err());
+    assert!(base64_decode("Zm9v!A==").is_err());
+}
@@ -0,0 +1,144 @@
+//! Verification of many signatures at once
+//!
+//! ECDSA signatures only commit to the x coordinate of the nonce point, so
+//! unlike Schnorr signatures they cannot be folded into one shared
+//! multi-scalar multiplication. Each ECDSA entry is therefore checked on its
+//! own (using Shamir's trick via `Signature::verify`) and the entries are
+//! spread across worker threads. There is no Schnorr implementation in this
+//! crate yet, so ECDSA is the only scheme the batch verifier handles.
+use std::thread;
+
+use programmingbitcoin::ecdsa::*;
+use programmingbitcoin::ellipticcurve::*;
+use programmingbitcoin::finitefield::*;
+
+/// A single signature to be checked as part of a batch
+pub struct BatchEntry {
+    pub public_key: Point,
+    pub message_hash: FieldElement,
+    pub signature: Signature
+}
+
+/// Collects signatures and verifies them together, reporting which failed.
+pub struct BatchVerifier {
+    entries: Vec<BatchEntry>,
+    threads: usize
+}
+
+impl BatchVerifier {
+    /// Create a new batch verifier that spreads its work across the given
+    /// number of threads.
+    pub fn new(threads: usize) -> BatchVerifier {
+        BatchVerifier { entries: Vec::new(), threads: threads.max(1) }
+    }
+
+    /// Create a new batch verifier using one thread per available CPU.
+    pub fn with_available_parallelism() -> BatchVerifier {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        Self::new(threads)
+    }
+
+    /// Add a signature to be verified against a public key and message hash.
+    pub fn add(&mut self, public_key: Point, message_hash: FieldElement, signature: Signature) {
+        self.entries.push(BatchEntry { public_key, message_hash, signature });
+    }
+
+    /// Returns the number of signatures in the batch
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Indicates whether or not the batch contains any signatures
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Verify every signature in the batch.
+    ///
+    /// Returns the indices, in the order the entries were added, of every
+    /// signature that failed verification.
+    pub fn verify(&self) -> Result<(), Vec<usize>> {
+        let failures =
+            if self.threads == 1 || self.entries.len() < 2 {
+                self.verify_sequential()
+            } else {
+                self.verify_parallel()
+            };
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    fn verify_sequential(&self) -> Vec<usize> {
+        self.entries.iter().enumerate().filter_map(|(index, entry)| {
+            if entry.signature.verify(&entry.public_key, &entry.message_hash) {
+                None
+            } else {
+                Some(index)
+            }
+        }).collect()
+    }
+
+    fn verify_parallel(&self) -> Vec<usize> {
+        let chunk_size = self.entries.len().div_ceil(self.threads);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = self.entries.chunks(chunk_size).enumerate().map(|(i, chunk)| {
+                scope.spawn(move || {
+                    chunk.iter().enumerate().filter_map(|(offset, entry)| {
+                        if entry.signature.verify(&entry.public_key, &entry.message_hash) {
+                            None
+                        } else {
+                            Some(i * chunk_size + offset)
+                        }
+                    }).collect::<Vec<usize>>()
+                })
+            }).collect();
+
+            workers.into_iter()
+                .flat_map(|worker| worker.join().expect("verification thread panicked"))
+                .collect()
+        })
+    }
+}
+
+#[test]
+fn test_batch_verification() {
+    use programmingbitcoin::messagedigest::*;
+    use rug::Integer;
+    use std::sync::Arc;
+
+    let curve = Arc::new(CryptographicCurve::new_secp256k1());
+    let mut sequential = BatchVerifier::new(1);
+    let mut parallel = BatchVerifier::new(3);
+
+    for i in 0..8 {
+        let secret = curve.make_element(hash256(format!("secret {}", i).as_bytes()).to_integer());
+        let private_key = PrivateKey::new(secret, &curve);
+        let message_hash = hash256(format!("message {}", i).as_bytes()).to_integer();
+        let signature = private_key.sign(&Integer::from(1000 + i), &message_hash);
+
+        // Tamper with the message of a couple of the entries
+        let message_hash =
+            if i == 2 || i == 7 {
+                curve.make_element(message_hash + 1)
+            } else {
+                curve.make_element(message_hash)
+            };
+
+        let copy = Signature::new(signature.r.clone(), signature.s.clone(), &curve);
+        sequential.add(private_key.public_key.clone(), message_hash.clone(), signature);
+        parallel.add(private_key.public_key.clone(), message_hash, copy);
+    }
+
+    assert_eq!(sequential.len(), 8);
+    assert_eq!(sequential.verify(), Err(vec![2, 7]));
+    assert_eq!(parallel.verify(), Err(vec![2, 7]));
+
+    let empty = BatchVerifier::new(4);
+    assert!(empty.is_empty());
+    assert_eq!(empty.verify(), Ok(()));
+}
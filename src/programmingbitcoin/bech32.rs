@@ -0,0 +1,264 @@
+//! Bech32 and Bech32m encoding and decoding
+//!
+//! A Bech32 string is a human-readable part, the separator '1', and data
+//! written as 5-bit values in a 32 character alphabet, ending with a six
+//! character BCH checksum. BIP173 defined Bech32; BIP350 defined Bech32m,
+//! which differs only in the constant the checksum is xored with and is used
+//! for segwit version 1 and later.
+static BECH32_ALPHABET : &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Generator coefficients of the BCH checksum
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+/// Number of characters in the checksum
+const CHECKSUM_LENGTH: usize = 6;
+
+/// Maximum length of a whole Bech32 string
+const MAX_LENGTH: usize = 90;
+
+/// Maximum length of the human-readable part
+const MAX_HRP_LENGTH: usize = 83;
+
+/// The checksum variant of a Bech32 string
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bech32Variant {
+    /// BIP173 Bech32, used for segwit version 0
+    Bech32,
+    /// BIP350 Bech32m, used for segwit version 1 and later
+    Bech32m
+}
+
+impl Bech32Variant {
+    /// Returns the value a valid checksum leaves behind
+    fn constant(self) -> u32 {
+        match self {
+            Bech32Variant::Bech32 => 1,
+            Bech32Variant::Bech32m => 0x2bc830a3
+        }
+    }
+}
+
+/// Computes the BCH checksum remainder of a sequence of 5-bit values
+fn polymod(values: &[u8]) -> u32 {
+    let mut checksum: u32 = 1;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ffffff) << 5 ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Expands the human-readable part into the values fed to the checksum: the
+/// high bits of each character, a zero, then the low bits of each character.
+fn hrp_expand(hrp: &[u8]) -> Vec<u8> {
+    let mut result: Vec<u8> = hrp.iter().map(|ch| ch >> 5).collect();
+    result.push(0);
+    result.extend(hrp.iter().map(|ch| ch & 0x1f));
+    result
+}
+
+/// Checks the human-readable part for length and printable ASCII characters
+fn check_hrp(hrp: &[u8]) -> Result<(), String> {
+    if hrp.is_empty() || hrp.len() > MAX_HRP_LENGTH {
+        return Err(format!("Invalid Bech32 human-readable part length {}", hrp.len()));
+    }
+    if let Some(ch) = hrp.iter().find(|&&ch| !(33..=126).contains(&ch)) {
+        return Err(format!("Invalid Bech32 human-readable part character {:#04x}", ch));
+    }
+    Ok(())
+}
+
+/// Encodes a human-readable part and 5-bit data values as a lowercase Bech32
+/// or Bech32m string.
+pub fn bech32_encode(hrp: &str, data: &[u8], variant: Bech32Variant) -> Result<String, String> {
+    if hrp.to_lowercase() != hrp && hrp.to_uppercase() != hrp {
+        return Err("Bech32 human-readable part has mixed case".to_string());
+    }
+    let hrp = hrp.to_lowercase();
+    check_hrp(hrp.as_bytes())?;
+    if hrp.len() + 1 + data.len() + CHECKSUM_LENGTH > MAX_LENGTH {
+        return Err("Bech32 string would be too long".to_string());
+    }
+    if data.iter().any(|&value| value >= 32) {
+        return Err("Bech32 data values must be 5 bits".to_string());
+    }
+
+    let mut values = hrp_expand(hrp.as_bytes());
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; CHECKSUM_LENGTH]);
+    let checksum = polymod(&values) ^ variant.constant();
+
+    let mut result = hrp;
+    result.push('1');
+    for &value in data {
+        result.push(BECH32_ALPHABET[value as usize] as char);
+    }
+    for i in 0..CHECKSUM_LENGTH {
+        let value = (checksum >> (5 * (CHECKSUM_LENGTH - 1 - i))) & 0x1f;
+        result.push(BECH32_ALPHABET[value as usize] as char);
+    }
+    Ok(result)
+}
+
+/// Decodes a Bech32 or Bech32m string, returning the lowercase
+/// human-readable part, the 5-bit data values without the checksum, and the
+/// variant whose checksum matched.
+///
+/// Strings may be all lowercase or all uppercase but not a mix of the two.
+pub fn bech32_decode(val: &str) -> Result<(String, Vec<u8>, Bech32Variant), String> {
+    if val.len() > MAX_LENGTH {
+        return Err(format!("Bech32 string is too long ({} characters)", val.len()));
+    }
+    if val.to_lowercase() != val && val.to_uppercase() != val {
+        return Err("Bech32 string has mixed case".to_string());
+    }
+    let val = val.to_lowercase();
+
+    let separator = val.rfind('1').ok_or_else(|| "Bech32 string has no separator".to_string())?;
+    let (hrp, data) = (&val.as_bytes()[..separator], &val.as_bytes()[separator + 1..]);
+    check_hrp(hrp)?;
+    if data.len() < CHECKSUM_LENGTH {
+        return Err("Bech32 checksum is too short".to_string());
+    }
+
+    let mut values = Vec::with_capacity(data.len());
+    for ch in data {
+        let value = BECH32_ALPHABET.iter().position(|a| a == ch)
+            .ok_or_else(|| format!("Invalid Bech32 character {:?}", *ch as char))?;
+        values.push(value as u8);
+    }
+
+    let mut expanded = hrp_expand(hrp);
+    expanded.extend_from_slice(&values);
+    let checksum = polymod(&expanded);
+    let variant = [Bech32Variant::Bech32, Bech32Variant::Bech32m].iter()
+        .find(|variant| variant.constant() == checksum)
+        .cloned()
+        .ok_or_else(|| "Bech32 checksum mismatch".to_string())?;
+
+    values.truncate(values.len() - CHECKSUM_LENGTH);
+    Ok((val[..separator].to_string(), values, variant))
+}
+
+/// Regroups a sequence of `from`-bit values into `to`-bit values, such as
+/// bytes into the 5-bit values Bech32 encodes.
+///
+/// With padding the final group is filled out with zero bits. Without it the
+/// leftover bits must be fewer than `from` and all zero, which is how 5-bit
+/// data is turned back into bytes.
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, String> {
+    let mut accumulator: u32 = 0;
+    let mut bits = 0;
+    let max_value = (1 << to) - 1;
+    let mut result = Vec::new();
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return Err(format!("Value {} does not fit in {} bits", value, from));
+        }
+        accumulator = (accumulator << from | value as u32) & ((1 << (from + to - 1)) - 1);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push(((accumulator >> bits) & max_value) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            result.push(((accumulator << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || (accumulator << (to - bits)) & max_value != 0 {
+        return Err("Invalid padding in converted bits".to_string());
+    }
+    Ok(result)
+}
+
+#[test]
+fn test_bech32_valid() {
+    // Test vectors from BIP173 and BIP350
+    let valid = [
+        ("A12UEL5L", Bech32Variant::Bech32),
+        ("a12uel5l", Bech32Variant::Bech32),
+        ("an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs", Bech32Variant::Bech32),
+        ("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw", Bech32Variant::Bech32),
+        ("11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j", Bech32Variant::Bech32),
+        ("split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w", Bech32Variant::Bech32),
+        ("?1ezyfcl", Bech32Variant::Bech32),
+        ("A1LQFN3A", Bech32Variant::Bech32m),
+        ("a1lqfn3a", Bech32Variant::Bech32m),
+        ("an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6", Bech32Variant::Bech32m),
+        ("abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx", Bech32Variant::Bech32m),
+        ("11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8", Bech32Variant::Bech32m),
+        ("split1checkupstagehandshakeupstreamerranterredcaperredlc445v", Bech32Variant::Bech32m),
+        ("?1v759aa", Bech32Variant::Bech32m)
+    ];
+    for &(string, variant) in valid.iter() {
+        let (hrp, data, decoded_variant) = bech32_decode(string).unwrap();
+        assert_eq!(decoded_variant, variant);
+        assert_eq!(bech32_encode(&hrp, &data, variant), Ok(string.to_lowercase()));
+    }
+
+    let (_, data, _) = bech32_decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw").unwrap();
+    assert_eq!(data, (0..32).collect::<Vec<u8>>());
+    assert!(bech32_encode("aBc", &[], Bech32Variant::Bech32).is_err());
+    assert!(bech32_encode("abc", &[32], Bech32Variant::Bech32).is_err());
+    assert!(bech32_encode("abc", &[0; 81], Bech32Variant::Bech32).is_err());
+}
+
+#[test]
+fn test_bech32_invalid() {
+    // Test vectors from BIP173 and BIP350, plus a mixed case string
+    let invalid = [
+        "\u{20}1nwldj5",
+        "\u{7f}1axkwrx",
+        "\u{80}1eym55h",
+        "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+        "pzry9x0s0muk",
+        "1pzry9x0s0muk",
+        "x1b4n0q5v",
+        "li1dgmt3",
+        "de1lg7wt\u{ff}",
+        "A1G7SGD8",
+        "10a06t8",
+        "1qzzfhee",
+        "\u{20}1xj0phk",
+        "\u{7f}1g6xzxy",
+        "\u{80}1vctc34",
+        "an84characterslonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11d6pts4",
+        "qyrz8wqd2c9m",
+        "1qyrz8wqd2c9m",
+        "y1b0jsk6g",
+        "lt1igcx5c0",
+        "in1muywd",
+        "mm1crxm3i",
+        "au1s5cgom",
+        "M1VUXWEZ",
+        "16plkw9",
+        "1p2gdwpf",
+        "a12UEL5L"
+    ];
+    for string in invalid.iter() {
+        assert!(bech32_decode(string).is_err(), "accepted {:?}", string);
+    }
+}
+
+#[test]
+fn test_convert_bits() {
+    let bytes = vec![0x75, 0x1e, 0x76, 0xe8, 0x19, 0x91, 0x96, 0xd4, 0x54, 0x94];
+    let values = convert_bits(&bytes, 8, 5, true).unwrap();
+    assert_eq!(values.len(), 16);
+    assert!(values.iter().all(|&value| value < 32));
+    assert_eq!(convert_bits(&values, 5, 8, false), Ok(bytes));
+
+    // Leftover bits must be zero and shorter than the input group size
+    assert_eq!(convert_bits(&[0xff], 8, 5, true), Ok(vec![31, 28]));
+    assert_eq!(convert_bits(&[31, 28], 5, 8, false), Ok(vec![0xff]));
+    assert!(convert_bits(&[31, 29], 5, 8, false).is_err());
+    assert!(convert_bits(&[0, 0, 0], 5, 8, false).is_err());
+    assert!(convert_bits(&[32], 5, 8, true).is_err());
+}
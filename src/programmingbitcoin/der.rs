@@ -0,0 +1,311 @@
+//! ASN.1 DER encoding and decoding
+//!
+//! Covers the handful of types needed for signatures and key files:
+//! INTEGER, BIT STRING, OCTET STRING, OBJECT IDENTIFIER, SEQUENCE and
+//! context-specific explicit tags. The reader only accepts DER, so forms that
+//! BER allows but DER forbids, such as indefinite or padded lengths and
+//! padded integers, are rejected.
+use rug::Integer;
+use rug::integer::Order;
+
+/// Tag of an INTEGER
+pub const TAG_INTEGER: u8 = 0x02;
+/// Tag of a BIT STRING
+pub const TAG_BIT_STRING: u8 = 0x03;
+/// Tag of an OCTET STRING
+pub const TAG_OCTET_STRING: u8 = 0x04;
+/// Tag of an OBJECT IDENTIFIER
+pub const TAG_OID: u8 = 0x06;
+/// Tag of a SEQUENCE
+pub const TAG_SEQUENCE: u8 = 0x30;
+
+/// Largest number of bytes accepted in a long form length
+const MAX_LENGTH_BYTES: usize = 4;
+
+/// Returns the tag of an explicit context-specific [number] value
+pub fn context_tag(number: u8) -> u8 {
+    assert!(number < 0x1f, "context tag numbers above 30 need the long form");
+    0xa0 | number
+}
+
+/// Encodes a value with the given tag, using the long length form when the
+/// content is 128 bytes or more.
+pub fn der_encode(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut result = vec![tag];
+    if content.len() < 0x80 {
+        result.push(content.len() as u8);
+    } else {
+        let length = content.len().to_be_bytes();
+        let skip = length.iter().take_while(|&&b| b == 0).count();
+        result.push(0x80 | (length.len() - skip) as u8);
+        result.extend_from_slice(&length[skip..]);
+    }
+    result.extend_from_slice(content);
+    result
+}
+
+/// Encodes a non-negative INTEGER in the fewest bytes, adding a leading zero
+/// byte when the top bit would otherwise mark it as negative.
+pub fn der_encode_integer(value: &Integer) -> Vec<u8> {
+    assert!(*value >= 0, "negative DER integers are not supported");
+    let mut content = vec![0x00];
+    content.extend(value.to_digits::<u8>(Order::MsfBe));
+    let skip = if content.len() > 1 && content[1] & 0x80 == 0 { 1 } else { 0 };
+    der_encode(TAG_INTEGER, &content[skip..])
+}
+
+/// Encodes an OCTET STRING
+pub fn der_encode_octet_string(bytes: &[u8]) -> Vec<u8> {
+    der_encode(TAG_OCTET_STRING, bytes)
+}
+
+/// Encodes a BIT STRING holding whole bytes
+pub fn der_encode_bit_string(bytes: &[u8]) -> Vec<u8> {
+    let mut content = vec![0x00];
+    content.extend_from_slice(bytes);
+    der_encode(TAG_BIT_STRING, &content)
+}
+
+/// Encodes an OBJECT IDENTIFIER from its arcs, such as [1, 3, 132, 0, 10]
+pub fn der_encode_oid(arcs: &[u64]) -> Vec<u8> {
+    assert!(arcs.len() >= 2 && arcs[0] <= 2 && (arcs[0] == 2 || arcs[1] < 40),
+            "invalid object identifier {:?}", arcs);
+
+    let mut content = Vec::new();
+    let first = arcs[0] * 40 + arcs[1];
+    for &arc in [first].iter().chain(&arcs[2..]) {
+        // Base 128, most significant group first, with the high bit set on
+        // every byte but the last
+        let mut groups = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            groups.push(0x80 | (rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+        groups.reverse();
+        content.extend(groups);
+    }
+    der_encode(TAG_OID, &content)
+}
+
+/// Encodes a SEQUENCE of already encoded values
+pub fn der_encode_sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    der_encode(TAG_SEQUENCE, &items.concat())
+}
+
+/// Reads DER values one after another from a buffer
+pub struct DerReader<'a> {
+    data: &'a [u8],
+    offset: usize
+}
+
+impl<'a> DerReader<'a> {
+    pub fn new(data: &'a [u8]) -> DerReader<'a> {
+        DerReader { data, offset: 0 }
+    }
+
+    /// Returns true once every value has been read
+    pub fn is_empty(&self) -> bool {
+        self.offset == self.data.len()
+    }
+
+    /// Returns the tag of the next value without reading it
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.get(self.offset).cloned()
+    }
+
+    /// Fails if any data is left unread
+    pub fn finish(&self) -> Result<(), String> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err("Unexpected trailing DER data".to_string())
+        }
+    }
+
+    /// Reads a value with the given tag and returns its content. Only
+    /// minimal, definite length encodings are accepted.
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8], String> {
+        let rest = &self.data[self.offset..];
+        if rest.len() < 2 || rest[0] != tag {
+            return Err(format!("Expected DER tag {:#04x}", tag));
+        }
+
+        let (length, header) = if rest[1] < 0x80 {
+            (rest[1] as usize, 2)
+        } else {
+            let count = (rest[1] & 0x7f) as usize;
+            if count == 0 {
+                return Err("Indefinite DER lengths are not allowed".to_string());
+            }
+            if count > MAX_LENGTH_BYTES || rest.len() < 2 + count {
+                return Err("Invalid DER length".to_string());
+            }
+            let length = rest[2..2 + count].iter().fold(0, |acc, &b| acc << 8 | b as usize);
+            if rest[2] == 0 || length < 0x80 {
+                return Err("DER length is not minimally encoded".to_string());
+            }
+            (length, 2 + count)
+        };
+
+        if rest.len() - header < length {
+            return Err("DER value is truncated".to_string());
+        }
+        self.offset += header + length;
+        Ok(&rest[header..header + length])
+    }
+
+    /// Reads a minimally encoded, non-negative INTEGER
+    pub fn read_integer(&mut self) -> Result<Integer, String> {
+        let bytes = self.read(TAG_INTEGER)?;
+        if bytes.is_empty() {
+            return Err("DER integer is empty".to_string());
+        }
+        if bytes[0] & 0x80 != 0 {
+            return Err("DER integer is negative".to_string());
+        }
+        if bytes.len() > 1 && bytes[0] == 0 && bytes[1] & 0x80 == 0 {
+            return Err("DER integer is not minimally encoded".to_string());
+        }
+        Ok(Integer::from_digits::<u8>(bytes, Order::MsfBe))
+    }
+
+    /// Reads an OCTET STRING
+    pub fn read_octet_string(&mut self) -> Result<&'a [u8], String> {
+        self.read(TAG_OCTET_STRING)
+    }
+
+    /// Reads a BIT STRING, which must hold whole bytes
+    pub fn read_bit_string(&mut self) -> Result<&'a [u8], String> {
+        match self.read(TAG_BIT_STRING)?.split_first() {
+            Some((&0, bytes)) => Ok(bytes),
+            _ => Err("DER bit string must hold whole bytes".to_string())
+        }
+    }
+
+    /// Reads an OBJECT IDENTIFIER and returns its arcs
+    pub fn read_oid(&mut self) -> Result<Vec<u64>, String> {
+        let bytes = self.read(TAG_OID)?;
+        if bytes.is_empty() || bytes[bytes.len() - 1] & 0x80 != 0 {
+            return Err("DER object identifier is truncated".to_string());
+        }
+
+        let mut arcs = Vec::new();
+        let mut arc: u64 = 0;
+        let mut start = true;
+        for &byte in bytes {
+            if start && byte == 0x80 {
+                return Err("DER object identifier is not minimally encoded".to_string());
+            }
+            if arc.leading_zeros() < 7 {
+                return Err("DER object identifier arc is too large".to_string());
+            }
+            arc = arc << 7 | (byte & 0x7f) as u64;
+            start = byte & 0x80 == 0;
+            if start {
+                if arcs.is_empty() {
+                    let first = if arc < 80 { arc / 40 } else { 2 };
+                    arcs.push(first);
+                    arcs.push(arc - first * 40);
+                } else {
+                    arcs.push(arc);
+                }
+                arc = 0;
+            }
+        }
+        Ok(arcs)
+    }
+
+    /// Reads a SEQUENCE and returns a reader over its contents
+    pub fn read_sequence(&mut self) -> Result<DerReader<'a>, String> {
+        Ok(DerReader::new(self.read(TAG_SEQUENCE)?))
+    }
+
+    /// Reads an explicit context-specific [number] value and returns a reader
+    /// over its contents.
+    pub fn read_context(&mut self, number: u8) -> Result<DerReader<'a>, String> {
+        Ok(DerReader::new(self.read(context_tag(number))?))
+    }
+}
+
+#[test]
+fn test_der_encoding() {
+    let integers = [
+        (0u32, vec![0x02, 0x01, 0x00]),
+        (127, vec![0x02, 0x01, 0x7f]),
+        (128, vec![0x02, 0x02, 0x00, 0x80]),
+        (256, vec![0x02, 0x02, 0x01, 0x00])
+    ];
+    for (value, encoded) in integers.iter() {
+        assert_eq!(der_encode_integer(&Integer::from(*value)), *encoded);
+        assert_eq!(DerReader::new(encoded).read_integer(), Ok(Integer::from(*value)));
+    }
+
+    // Long form lengths
+    let content = vec![0xab; 200];
+    let encoded = der_encode_octet_string(&content);
+    assert_eq!(encoded[..3], [0x04, 0x81, 0xc8]);
+    assert_eq!(DerReader::new(&encoded).read_octet_string(), Ok(&content[..]));
+    let content = vec![0xab; 300];
+    let encoded = der_encode_bit_string(&content);
+    assert_eq!(encoded[..5], [0x03, 0x82, 0x01, 0x2d, 0x00]);
+    assert_eq!(DerReader::new(&encoded).read_bit_string(), Ok(&content[..]));
+
+    let large = Integer::from(1) << 1100;
+    let encoded = der_encode_sequence(&[der_encode_integer(&large), der_encode_integer(&large)]);
+    let mut sequence = DerReader::new(&encoded).read_sequence().unwrap();
+    assert_eq!(sequence.read_integer(), Ok(large.clone()));
+    assert_eq!(sequence.read_integer(), Ok(large));
+    assert_eq!(sequence.finish(), Ok(()));
+
+    // 1.2.840.10045.2.1 and 1.3.132.0.10
+    let oids: [(&[u64], &[u8]); 2] = [
+        (&[1, 2, 840, 10045, 2, 1], &[0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01]),
+        (&[1, 3, 132, 0, 10], &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a])
+    ];
+    for &(arcs, encoded) in &oids {
+        assert_eq!(der_encode_oid(arcs), encoded);
+        assert_eq!(DerReader::new(encoded).read_oid(), Ok(arcs.to_vec()));
+    }
+
+    let tagged = der_encode(context_tag(1), &der_encode_oid(&[2, 999, 3]));
+    assert_eq!(tagged[..5], [0xa1, 0x05, 0x06, 0x03, 0x88]);
+    let mut reader = DerReader::new(&tagged);
+    assert_eq!(reader.read_context(1).unwrap().read_oid(), Ok(vec![2, 999, 3]));
+}
+
+#[test]
+fn test_der_rejects_ber() {
+    let invalid: [&[u8]; 10] = [
+        // Indefinite, padded and short long-form lengths
+        &[0x30, 0x80, 0x00, 0x00],
+        &[0x04, 0x82, 0x00, 0x01, 0xff],
+        &[0x04, 0x81, 0x01, 0xff],
+        // Truncated value
+        &[0x04, 0x02, 0xff],
+        // Empty, padded and negative integers
+        &[0x02, 0x00],
+        &[0x02, 0x02, 0x00, 0x7f],
+        &[0x02, 0x01, 0x80],
+        // Bit strings with unused bits, padded and truncated identifiers
+        &[0x03, 0x02, 0x01, 0xfe],
+        &[0x06, 0x02, 0x80, 0x01],
+        &[0x06, 0x01, 0x81]
+    ];
+    for data in invalid.iter() {
+        let mut reader = DerReader::new(data);
+        let result = match data[0] {
+            TAG_INTEGER => reader.read_integer().map(|_| ()),
+            TAG_BIT_STRING => reader.read_bit_string().map(|_| ()),
+            TAG_OID => reader.read_oid().map(|_| ()),
+            tag => reader.read(tag).map(|_| ())
+        };
+        assert!(result.is_err(), "accepted {:02x?}", data);
+    }
+
+    // Trailing data and unexpected tags
+    let mut reader = DerReader::new(&[0x05, 0x00, 0x05, 0x00]);
+    assert!(reader.read(TAG_SEQUENCE).is_err());
+    assert!(reader.read(0x05).is_ok());
+    assert!(reader.finish().is_err());
+}
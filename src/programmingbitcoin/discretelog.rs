@@ -0,0 +1,241 @@
+//! Discrete logarithm solvers for small curves
+//!
+//! Given points P and G, these find k with kG = P. Both baby-step giant-step
+//! and Pollard's rho take around √n group operations for a generator of
+//! order n, which is hopeless for the 2^256 order of secp256k1 but quick on
+//! the deliberately small curves used in teaching.
+use rug::Integer;
+use std::collections::HashMap;
+
+use programmingbitcoin::ellipticcurve::*;
+
+/// Number of Pollard rho steps between calls to the progress callback
+const PROGRESS_INTERVAL: u64 = 1 << 10;
+
+/// Number of times Pollard's rho restarts from a new starting point before
+/// giving up.
+const MAX_RHO_RESTARTS: u32 = 16;
+
+/// Progress of a running Pollard rho search
+#[derive(Clone, Debug, PartialEq)]
+pub struct RhoProgress {
+    /// Total number of steps taken by the slower walk so far
+    pub steps: u64,
+    /// Number of times the search has restarted after a useless collision
+    pub restarts: u32
+}
+
+impl Point {
+    /// Find k in [0, order) with k * generator = self.
+    ///
+    /// Uses baby-step giant-step, which is deterministic but stores around
+    /// √order points. Returns None if self is not a multiple of the
+    /// generator.
+    pub fn discrete_log(&self, generator: &Point, order: &Integer) -> Option<Integer> {
+        discrete_log_bsgs(self, generator, order)
+    }
+}
+
+/// Solves kG = P by baby-step giant-step.
+///
+/// With m = ⌈√n⌉, every k below n can be written as im + j with i, j < m.
+/// The baby steps jG are stored in a table, and the giant steps P - imG are
+/// looked up in it until a match gives k.
+pub fn discrete_log_bsgs(point: &Point, generator: &Point, order: &Integer) -> Option<Integer> {
+    let steps = Integer::from(order - 1).sqrt() + 1u32;
+    let steps_count = steps.to_usize().expect("order is too large for baby-step giant-step");
+
+    let mut baby_steps = HashMap::new();
+    let mut current = Point::identity(&generator.curve);
+    for j in 0..steps_count {
+        baby_steps.entry(point_key(&current, false)).or_insert(j);
+        current = &current + generator;
+    }
+
+    // Each giant step subtracts mG, which is (n - m)G
+    let giant_step = generator * Integer::from(order - &steps);
+    let mut current = point.clone();
+    for i in 0..steps_count {
+        if let Some(&j) = baby_steps.get(&point_key(&current, false)) {
+            let k = Integer::from(i * steps_count + j) % order;
+            return Some(k);
+        }
+        current = &current + &giant_step;
+    }
+    None
+}
+
+/// A point aG + bP on a Pollard rho walk, along with its coefficients
+#[derive(Clone)]
+struct RhoState {
+    point: Point,
+    a: Integer,
+    b: Integer
+}
+
+impl RhoState {
+    /// Take one step of the walk, choosing between adding G, doubling and
+    /// adding P based on the x coordinate of the current point.
+    fn step(&mut self, generator: &Point, target: &Point, order: &Integer) {
+        let partition = match self.point.x.as_ref() {
+            Some(x) => x.value.mod_u(3),
+            None => 0
+        };
+        match partition {
+            0 => {
+                self.point = &self.point + generator;
+                self.a += 1;
+            },
+            1 => {
+                self.point = &self.point + &self.point;
+                self.a *= 2;
+                self.b *= 2;
+            },
+            _ => {
+                self.point = &self.point + target;
+                self.b += 1;
+            }
+        }
+        self.a %= order;
+        self.b %= order;
+    }
+}
+
+/// Solves kG = P by Pollard's rho.
+///
+/// A pseudo-random walk over points aG + bP is followed until it cycles,
+/// which Floyd's cycle detection spots by moving a second walk at twice the
+/// speed. A collision aG + bP = a'G + b'P gives (b - b')k = a' - a modulo the
+/// order. Unlike baby-step giant-step this needs almost no memory.
+///
+/// The progress callback is invoked every 1024 steps, on every restart and
+/// once more when the logarithm is found.
+/// Returns None if self is not a multiple of the generator or if the walk
+/// keeps producing useless collisions.
+pub fn discrete_log_rho<C>(point: &Point,
+                           generator: &Point,
+                           order: &Integer,
+                           mut progress: C) -> Option<Integer>
+    where C: FnMut(&RhoProgress)
+{
+    if point.is_identity() {
+        return Some(Integer::from(0));
+    }
+
+    let mut status = RhoProgress { steps: 0, restarts: 0 };
+    while status.restarts < MAX_RHO_RESTARTS {
+        // Start each attempt from a different combination of G and P
+        let a = Integer::from(status.restarts + 1) % order;
+        let b = Integer::from(2 * status.restarts + 1) % order;
+        let start = &(generator * &a) + &(point * &b);
+        let mut tortoise = RhoState { point: start, a, b };
+        let mut hare = tortoise.clone();
+
+        loop {
+            tortoise.step(generator, point, order);
+            hare.step(generator, point, order);
+            hare.step(generator, point, order);
+            status.steps += 1;
+            if status.steps.is_multiple_of(PROGRESS_INTERVAL) {
+                progress(&status);
+            }
+            if tortoise.point == hare.point {
+                break;
+            }
+        }
+
+        let denominator = Integer::from(&tortoise.b - &hare.b);
+        let numerator = Integer::from(&hare.a - &tortoise.a);
+        if let Some(k) = solve_collision(point, generator, order, numerator, denominator) {
+            progress(&status);
+            return Some(k);
+        }
+
+        status.restarts += 1;
+        progress(&status);
+    }
+    None
+}
+
+/// Solves dk = c modulo the order, returning the solution for which kG = P.
+///
+/// When d shares a factor g with the order there are g candidate solutions,
+/// which are checked one at a time.
+fn solve_collision(point: &Point,
+                   generator: &Point,
+                   order: &Integer,
+                   numerator: Integer,
+                   denominator: Integer) -> Option<Integer>
+{
+    let numerator = numerator % order;
+    let denominator = denominator % order;
+    let gcd = denominator.clone().gcd(order);
+    if gcd == *order || !numerator.is_divisible(&gcd) {
+        return None;
+    }
+
+    let reduced_order = Integer::from(order / &gcd);
+    let inverse = Integer::from(&denominator / &gcd).invert(&reduced_order).ok()?;
+    let mut k = (Integer::from(&numerator / &gcd) * inverse) % &reduced_order;
+    if k < 0 {
+        k += &reduced_order;
+    }
+
+    let mut candidate = 0;
+    while gcd > candidate {
+        if (generator * &k) == *point {
+            return Some(k);
+        }
+        k += &reduced_order;
+        candidate += 1;
+    }
+    None
+}
+
+#[test]
+fn test_discrete_log_book_curve() {
+    use programmingbitcoin::finitefield::*;
+    use std::sync::Arc;
+
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let fec = FiniteEllipticCurve::new(EllipticCurve::new(Integer::from(0), Integer::from(7)), &gf_223);
+
+    // (15, 86) has order 7 and (47, 71) has order 21
+    for &(x, y, order) in &[(15, 86, 7), (47, 71, 21)] {
+        let generator = fec.make_point_integral(Integer::from(x), Integer::from(y)).unwrap();
+        let order = Integer::from(order);
+        let mut k = Integer::from(0);
+        while k < order {
+            let point = &generator * &k;
+            assert_eq!(point.discrete_log(&generator, &order), Some(k.clone()));
+            assert_eq!(discrete_log_rho(&point, &generator, &order, |_| {}), Some(k.clone()));
+            k += 1;
+        }
+    }
+
+    // (47, 71) has order 21, so it is not a multiple of (15, 86)
+    let generator = fec.make_point_integral(Integer::from(15), Integer::from(86)).unwrap();
+    let outside = fec.make_point_integral(Integer::from(47), Integer::from(71)).unwrap();
+    assert_eq!(outside.discrete_log(&generator, &Integer::from(7)), None);
+}
+
+#[test]
+fn test_discrete_log_weak_curve() {
+    use programmingbitcoin::finitefield::*;
+    use std::sync::Arc;
+
+    // y^2 = x^3 + 2x + 5 over F_100003 has a prime number of points, 99907
+    let field = Arc::new(GaloisField::new(Integer::from(100003)));
+    let fec = FiniteEllipticCurve::new(EllipticCurve::new(Integer::from(2), Integer::from(5)), &field);
+    let generator = fec.make_point_integral(Integer::from(2), Integer::from(40359)).unwrap();
+    let order = Integer::from(99907);
+
+    let secret = Integer::from(77777);
+    let point = &generator * &secret;
+    assert_eq!(point.discrete_log(&generator, &order), Some(secret.clone()));
+
+    let mut reports = Vec::new();
+    assert_eq!(discrete_log_rho(&point, &generator, &order, |p| reports.push(p.clone())), Some(secret));
+    assert!(!reports.is_empty());
+    assert!(reports.windows(2).all(|pair| pair[0].steps <= pair[1].steps));
+}
@@ -1,6 +1,6 @@
 //! Elliptic-Curve Digital Signature Algorithm (ECDSA) implementation
 use std::iter;
-use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
 
 use rug::Integer;
 use rug::integer::Order;
@@ -14,7 +14,9 @@ use programmingbitcoin::messagedigest::*;
 pub struct CryptographicCurve {
     pub finite_curve: FiniteEllipticCurve,
     pub generator_point: Point,
-    pub order: Rc<GaloisField>
+    pub order: Arc<GaloisField>,
+    /// The number of curve points divided by the order of the generator
+    pub cofactor: Integer
 }
 
 impl CryptographicCurve {
@@ -22,11 +24,22 @@ impl CryptographicCurve {
     pub fn new(curve: FiniteEllipticCurve,
                generator_point: Point,
                order: Integer) -> CryptographicCurve
+    {
+        Self::with_cofactor(curve, generator_point, order, Integer::from(1))
+    }
+
+    /// Create a new cryptographic curve whose generator spans a subgroup of
+    /// the given cofactor
+    pub fn with_cofactor(curve: FiniteEllipticCurve,
+                         generator_point: Point,
+                         order: Integer,
+                         cofactor: Integer) -> CryptographicCurve
     {
         CryptographicCurve {
             finite_curve: curve,
             generator_point: generator_point,
-            order: Rc::new(GaloisField::new(order))
+            order: Arc::new(GaloisField::new(order)),
+            cofactor
         }
     }
 
@@ -44,7 +57,7 @@ impl CryptographicCurve {
         ).unwrap();
         let p = Integer::from(2).pow(256) - Integer::from(2).pow(32) - Integer::from(977);
 
-        let field = Rc::new(GaloisField::new(p));
+        let field = Arc::new(GaloisField::new(p));
         let curve = EllipticCurve::new(Integer::from(0), Integer::from(7));
         let secp256k1_curve = FiniteEllipticCurve::new(curve, &field);
         let generator_point = secp256k1_curve.make_point_integral(gx, gy).unwrap();
@@ -52,6 +65,15 @@ impl CryptographicCurve {
         CryptographicCurve::new(secp256k1_curve, generator_point, order)
     }
 
+    /// Returns a shared secp256k1 curve, creating it on first use.
+    ///
+    /// The curve is built once per process and can be handed to any number
+    /// of threads.
+    pub fn secp256k1() -> Arc<CryptographicCurve> {
+        static SECP256K1: OnceLock<Arc<CryptographicCurve>> = OnceLock::new();
+        SECP256K1.get_or_init(|| Arc::new(CryptographicCurve::new_secp256k1())).clone()
+    }
+
     /// Make element modulo the order of the curve
     pub fn make_element(&self, value: Integer) -> FieldElement {
         // We need to do our scalar arithmetic modulo the curve's order instead
@@ -63,17 +85,56 @@ impl CryptographicCurve {
     pub fn make_point_integral(&self, x: Integer, y: Integer) -> Result<Point,String> {
         self.finite_curve.make_point_integral(x,y)
     }
+
+    /// Indicates whether or not a point lies in the prime-order subgroup
+    /// generated by the curve's generator point.
+    ///
+    /// The point must be on the curve and multiplying it by the group order
+    /// must give the identity. Points outside the subgroup can be used in
+    /// small subgroup attacks, so untrusted points should be checked.
+    pub fn is_in_subgroup(&self, point: &Point) -> bool {
+        if point.curve != self.finite_curve {
+            return false;
+        }
+        match (point.x.as_ref(), point.y.as_ref()) {
+            (Some(x), Some(y)) => {
+                self.finite_curve.on_curve(x, y) && (point * &self.order.prime).is_identity()
+            },
+            (None, None) => true,
+            _ => false
+        }
+    }
+
+    /// Maps a point on the curve into the prime-order subgroup by
+    /// multiplying it by the cofactor.
+    pub fn clear_cofactor(&self, point: &Point) -> Point {
+        point * &self.cofactor
+    }
+}
+
+impl Point {
+    /// Indicates whether or not this point lies in the prime-order subgroup
+    /// of the given curve. See `CryptographicCurve::is_in_subgroup`.
+    pub fn is_in_subgroup(&self, curve: &CryptographicCurve) -> bool {
+        curve.is_in_subgroup(self)
+    }
+
+    /// Maps this point into the prime-order subgroup of the given curve by
+    /// multiplying it by the cofactor.
+    pub fn clear_cofactor(&self, curve: &CryptographicCurve) -> Point {
+        curve.clear_cofactor(self)
+    }
 }
 
 /// An ECDSA signature result
 pub struct Signature {
     pub r: FieldElement,
     pub s: FieldElement,
-    pub curve: Rc<CryptographicCurve>
+    pub curve: Arc<CryptographicCurve>
 }
 
 impl Signature {
-    pub fn new(r: FieldElement, s: FieldElement, curve: &Rc<CryptographicCurve>) -> Signature {
+    pub fn new(r: FieldElement, s: FieldElement, curve: &Arc<CryptographicCurve>) -> Signature {
         Signature { r: r, s: s, curve: curve.clone() }
     }
 
@@ -81,7 +142,11 @@ impl Signature {
     pub fn verify(&self, public_key: &Point, message_hash: &FieldElement) -> bool {
         let u = message_hash / &self.s;
         let v = &self.r / &self.s;
-        let total = &u * &self.curve.generator_point + &v * public_key;
+        // Shamir's trick: compute u*G + v*P with a single chain of doublings
+        let total = Point::multi_mul(&[
+            (u.value, self.curve.generator_point.clone()),
+            (v.value, public_key.clone())
+        ], &self.curve.finite_curve);
 
         // The x coordinate of the resulting point should match the r value of
         // the signature
@@ -97,12 +162,12 @@ impl Signature {
 pub struct PrivateKey {
     pub secret: FieldElement,
     pub public_key: Point,
-    curve: Rc<CryptographicCurve>
+    curve: Arc<CryptographicCurve>
 }
 
 impl PrivateKey {
     /// Create a new private key from the given secret on the given curve
-    pub fn new(secret: FieldElement, curve: &Rc<CryptographicCurve>) -> PrivateKey {
+    pub fn new(secret: FieldElement, curve: &Arc<CryptographicCurve>) -> PrivateKey {
         PrivateKey {
             secret: secret.clone(),
             public_key: &secret * &curve.generator_point,
@@ -186,7 +251,7 @@ pub fn nonce_generator_rfc6979(message: &Vec<u8>,
 
 #[test]
 fn test_signature_verification() {
-    let curve = Rc::new(CryptographicCurve::new_secp256k1());
+    let curve = Arc::new(CryptographicCurve::new_secp256k1());
     let point = curve.make_point_integral(
         Integer::from_str_radix(
             "887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c", 16
@@ -218,6 +283,36 @@ fn test_signature_verification() {
     }
 }
 
+#[test]
+fn test_thread_safety() {
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<CryptographicCurve>();
+    assert_send_sync::<Signature>();
+    assert_send_sync::<PrivateKey>();
+    assert_send_sync::<Point>();
+    assert_send_sync::<FieldElement>();
+
+    let curve = CryptographicCurve::secp256k1();
+    assert!(Arc::ptr_eq(&curve, &CryptographicCurve::secp256k1()));
+
+    let workers: Vec<_> = (1..5).map(|i| {
+        thread::spawn(move || {
+            let curve = CryptographicCurve::secp256k1();
+            let private_key = PrivateKey::new(curve.make_element(Integer::from(i * 1000)), &curve);
+            let message = Integer::from(i);
+            let signature = private_key.sign(&Integer::from(i + 7), &message);
+            (private_key.public_key, signature, curve.make_element(message))
+        })
+    }).collect();
+
+    for worker in workers {
+        let (public_key, signature, message) = worker.join().unwrap();
+        assert!(signature.verify(&public_key, &message));
+    }
+}
+
 #[test]
 fn test_signing() {
     use programmingbitcoin::messagedigest::*;
@@ -232,11 +327,11 @@ fn test_signing() {
         )
     ];
 
-    let curve = Rc::new(CryptographicCurve::new_secp256k1());
+    let curve = Arc::new(CryptographicCurve::new_secp256k1());
     for (secret, msg, nonce, r, s) in expectations {
-        let e = curve.make_element(hash256_integer(secret.as_bytes()));
+        let e = curve.make_element(hash256(secret.as_bytes()).to_integer());
         let priv_key = PrivateKey::new(e.clone(), &curve);
-        let msg_hash = hash256_integer(msg.as_bytes());
+        let msg_hash = hash256(msg.as_bytes()).to_integer();
         let sig = priv_key.sign(&Integer::from(nonce), &msg_hash);
 
         assert_eq!(sig.r.value, Integer::from_str_radix(r, 16).unwrap());
@@ -244,3 +339,32 @@ fn test_signing() {
         assert!(sig.verify(&priv_key.public_key, &curve.make_element(msg_hash)));
     }
 }
+
+#[test]
+fn test_subgroup_membership() {
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let ec = EllipticCurve::new(Integer::from(0), Integer::from(7));
+    let fec = FiniteEllipticCurve::new(ec, &gf_223);
+
+    // The curve has 252 = 7 * 36 points, and (15, 86) generates the subgroup
+    // of order 7
+    let generator = fec.make_point_integral(Integer::from(15), Integer::from(86)).unwrap();
+    let curve = CryptographicCurve::with_cofactor(fec.clone(), generator.clone(), Integer::from(7), Integer::from(36));
+
+    for k in 0..7 {
+        assert!((&generator * Integer::from(k)).is_in_subgroup(&curve));
+    }
+
+    let outside = fec.make_point_integral(Integer::from(47), Integer::from(71)).unwrap();
+    assert!(!curve.is_in_subgroup(&outside));
+    assert!(!outside.is_in_subgroup(&curve));
+    let cleared = outside.clear_cofactor(&curve);
+    assert!(cleared.is_in_subgroup(&curve));
+    assert_eq!(curve.clear_cofactor(&outside), cleared);
+    assert_eq!(cleared.order(), 7);
+
+    // A point off the curve is never in the subgroup
+    let x = FieldElement::new(Integer::from(200), &gf_223);
+    let y = FieldElement::new(Integer::from(119), &gf_223);
+    assert!(!curve.is_in_subgroup(&Point::new(Some(x), Some(y), &fec)));
+}
@@ -1,9 +1,35 @@
 use programmingbitcoin::finitefield::*;
 
 use rug::Integer;
+use rug::integer::IsPrime;
+use rug::ops::DivRounding;
+use std::collections::HashMap;
 use std::fmt;
 use std::ops::{Add, Mul};
-use std::rc::Rc;
+use std::sync::Arc;
+
+/// Number of terms above which `Point::multi_mul` switches from Strauss-Shamir
+/// interleaving to Pippenger's bucket method.
+const PIPPENGER_THRESHOLD: usize = 32;
+
+/// Width in bits of the windows used by Strauss-Shamir interleaving.
+const STRAUSS_WINDOW_BITS: u32 = 4;
+
+/// Fields below this size have their points counted one x coordinate at a
+/// time by `FiniteEllipticCurve::count_points`.
+const NAIVE_COUNT_LIMIT: u32 = 1 << 12;
+
+/// Largest number of baby steps `FiniteEllipticCurve::count_points_bsgs`
+/// will take, which limits it to fields of up to around 60 bits.
+const BSGS_MAX_STEPS: usize = 1 << 16;
+
+/// Number of points tried on the curve and on its twist by
+/// `FiniteEllipticCurve::count_points_bsgs` before it gives up.
+const BSGS_COUNT_ATTEMPTS: u32 = 64;
+
+/// Factors below this bound are found by trial division, and larger ones by
+/// Pollard's rho.
+const TRIAL_DIVISION_LIMIT: u32 = 1 << 10;
 
 /// Represents an elliptic curve over points satisfying y^2 = x^3 + ax + b
 #[derive(Clone, Debug, PartialEq)]
@@ -20,31 +46,211 @@ impl EllipticCurve {
 }
 
 /// Represents an elliptic curve over a finite field
+///
+/// The curve is generic over the representation of its field elements and
+/// defaults to `FieldElement`.
 #[derive(Clone, Debug, PartialEq)]
-pub struct FiniteEllipticCurve {
+pub struct FiniteEllipticCurve<F: Field = FieldElement> {
     pub curve: EllipticCurve,
-    pub field: Rc<GaloisField>
+    pub field: F::Context,
+    a: F,
+    b: F
 }
 
 impl FiniteEllipticCurve {
     /// Creates a new finite elliptic curve by comibing a curve and Galois field
-    pub fn new(curve: EllipticCurve, field: &Rc<GaloisField>) -> FiniteEllipticCurve {
-        FiniteEllipticCurve { curve: curve, field: field.clone() }
+    pub fn new(curve: EllipticCurve, field: &Arc<GaloisField>) -> FiniteEllipticCurve {
+        Self::with_field(curve, field)
+    }
+
+    /// Returns x^3 + ax + b for the given x coordinate
+    fn right_hand_side(&self, x: &FieldElement) -> FieldElement {
+        &(&(&(x * x) * x) + &(&self.a * x)) + &self.b
+    }
+
+    /// Indicates whether or not the curve is singular, in which case its
+    /// points do not form a group.
+    ///
+    /// Over odd primes this is when the discriminant 4a^3 + 27b^2 is zero.
+    /// Over F_2 a curve of the form y^2 = x^3 + ax + b is always singular.
+    pub fn is_singular(&self) -> bool {
+        if self.field.prime == 2 {
+            return true;
+        }
+        let a_cubed = &(&self.a * &self.a) * &self.a;
+        let b_squared = &self.b * &self.b;
+        let discriminant = &(&self.make_element(Integer::from(4)) * &a_cubed) +
+            &(&self.make_element(Integer::from(27)) * &b_squared);
+        discriminant.is_zero()
+    }
+
+    /// Returns every point on the curve, starting with the identity.
+    ///
+    /// Every x coordinate in the field is tried, so this is only suitable
+    /// for small fields.
+    pub fn points(&self) -> Vec<Point> {
+        let mut points = vec![Point::identity(self)];
+        let mut x_raw = Integer::from(0);
+        while x_raw < self.field.prime {
+            let x = self.make_element(x_raw.clone());
+            if let Some(y) = Field::sqrt(&self.right_hand_side(&x)) {
+                if !y.is_zero() {
+                    points.push(Point::new(Some(x.clone()), Some(-&y), self));
+                }
+                points.push(Point::new(Some(x), Some(y), self));
+            }
+            x_raw += 1;
+        }
+        points
+    }
+
+    /// Returns the number of points on the curve, including the identity.
+    ///
+    /// Small fields are counted naively and larger ones with baby-step
+    /// giant-step. Returns an error for singular curves and for fields too
+    /// large to count.
+    pub fn count_points(&self) -> Result<Integer, String> {
+        if self.field.prime < NAIVE_COUNT_LIMIT {
+            self.count_points_naive()
+        } else {
+            self.count_points_bsgs()
+        }
+    }
+
+    /// Counts the points by checking whether x^3 + ax + b is a square for
+    /// every x in the field, which takes time proportional to p.
+    pub fn count_points_naive(&self) -> Result<Integer, String> {
+        if self.is_singular() {
+            return Err("Cannot count the points of a singular curve".to_string());
+        }
+        let prime = &self.field.prime;
+        let mut count = Integer::from(1);
+        let mut x = Integer::from(0);
+        while x < *prime {
+            let rhs = self.right_hand_side(&self.make_element(x.clone()));
+            count += 1 + rhs.value.legendre(prime);
+            x += 1;
+        }
+        Ok(count)
+    }
+
+    /// Counts the points using Mestre's baby-step giant-step method.
+    ///
+    /// By Hasse's theorem the count N lies within 2√p of p + 1. For a point
+    /// P, baby-step giant-step finds a multiple of its order in that
+    /// interval in around p^(1/4) steps, and from it the order itself. The
+    /// quadratic twist of the curve has 2p + 2 - N points, so the orders of
+    /// points on the twist constrain N as well. Once only one N in the
+    /// interval fits the orders found on both curves, that is the count.
+    ///
+    /// Returns an error for singular curves, for fields too large to search
+    /// and if the points tried do not pin the count down, which can happen
+    /// for very small fields.
+    pub fn count_points_bsgs(&self) -> Result<Integer, String> {
+        if self.is_singular() {
+            return Err("Cannot count the points of a singular curve".to_string());
+        }
+        let prime = &self.field.prime;
+        let bound = Integer::from(prime * 4).sqrt();
+        let low = Integer::from(prime + 1) - &bound;
+        let high = Integer::from(prime + 1) + &bound;
+        let twist_sum = Integer::from(prime + 1) * 2;
+        let twist = self.quadratic_twist();
+
+        // N is a multiple of lcm and 2p + 2 - N a multiple of twist_lcm
+        let (mut lcm, mut twist_lcm) = (Integer::from(1), Integer::from(1));
+        let (mut x, mut twist_x) = (Integer::from(0), Integer::from(0));
+        for _ in 0..BSGS_COUNT_ATTEMPTS {
+            if let Some(point) = self.next_point(&mut x) {
+                lcm.lcm_mut(&point.order_in_range(&low, &bound)?);
+            }
+            if let Some(point) = twist.next_point(&mut twist_x) {
+                twist_lcm.lcm_mut(&point.order_in_range(&low, &bound)?);
+            }
+
+            // Every N = 0 mod lcm with N = 2p + 2 mod twist_lcm is congruent
+            // to offset modulo the combined modulus
+            let gcd = Integer::from(lcm.gcd_ref(&twist_lcm));
+            let target = Integer::from(&twist_sum % &twist_lcm);
+            let reduced_modulus = Integer::from(&twist_lcm / &gcd);
+            let inverse = Integer::from(&lcm / &gcd).invert(&reduced_modulus).unwrap_or_default();
+            if !target.is_divisible(&gcd) {
+                return Err("Point orders are inconsistent with the curve".to_string());
+            }
+            let offset = Integer::from(&target / &gcd) * inverse % &reduced_modulus * &lcm;
+            let modulus = Integer::from(&lcm * &reduced_modulus);
+
+            // The count is found once the interval holds only one candidate
+            let first = Integer::from(&low - &offset).div_ceil(&modulus) * &modulus + offset;
+            if Integer::from(&first + &modulus) > high {
+                return Ok(first);
+            }
+        }
+        Err("Could not determine the number of points".to_string())
+    }
+
+    /// Returns the quadratic twist y^2 = x^3 + ad^2x + bd^3 for the smallest
+    /// quadratic non-residue d. An x coordinate that has no point on one of
+    /// the two curves has two on the other.
+    fn quadratic_twist(&self) -> FiniteEllipticCurve {
+        let prime = &self.field.prime;
+        let mut d = Integer::from(2);
+        while d.legendre(prime) != -1 {
+            d += 1;
+        }
+        let d = self.make_element(d);
+        let a = &self.a * &(&d * &d);
+        let b = &self.b * &(&(&d * &d) * &d);
+        FiniteEllipticCurve::new(EllipticCurve::new(a.value, b.value), &self.field)
+    }
+
+    /// Returns the first point with an x coordinate of at least x, leaving x
+    /// just past it. Returns None once every x coordinate has been tried.
+    fn next_point(&self, x: &mut Integer) -> Option<Point> {
+        while *x < self.field.prime {
+            let fx = self.make_element(x.clone());
+            *x += 1;
+            if let Some(y) = Field::sqrt(&self.right_hand_side(&fx)) {
+                return Some(Point::new(Some(fx), Some(y), self));
+            }
+        }
+        None
+    }
+
+    /// Indicates whether or not the point generates every point on the curve
+    pub fn is_generator(&self, point: &Point) -> Result<bool, String> {
+        let count = self.count_points()?;
+        Ok(point.curve == *self && point.order_dividing(&count) == count)
+    }
+
+    /// Returns every point that generates the whole group of points on the
+    /// curve. The result is empty when the group is not cyclic.
+    pub fn generators(&self) -> Result<Vec<Point>, String> {
+        let count = self.count_points()?;
+        Ok(self.points().into_iter().filter(|point| point.order_dividing(&count) == count).collect())
+    }
+}
+
+impl<F: Field> FiniteEllipticCurve<F> {
+    /// Creates a new finite elliptic curve over any field representation
+    pub fn with_field(curve: EllipticCurve, field: &F::Context) -> FiniteEllipticCurve<F> {
+        let a = F::make_element(field, curve.a.clone());
+        let b = F::make_element(field, curve.b.clone());
+        FiniteEllipticCurve { curve, field: field.clone(), a, b }
     }
 
     /// Indicates whether or not the given coordinates are valid on this finite EC.
-    pub fn on_curve(&self, x: &FieldElement, y: &FieldElement) -> bool {
-        assert!(x.field == y.field);
-        y.pow(&Integer::from(2)) ==
-            x.pow(&Integer::from(3)) + &self.curve.a * x + &self.curve.b
+    pub fn on_curve(&self, x: &F, y: &F) -> bool {
+        y.clone() * y.clone() ==
+            x.clone() * x.clone() * x.clone() + self.a.clone() * x.clone() + self.b.clone()
     }
 
-    pub fn make_element(&self, value: Integer) -> FieldElement {
-        FieldElement::new(value, &self.field)
+    pub fn make_element(&self, value: Integer) -> F {
+        F::make_element(&self.field, value)
     }
 
     /// Makes a new point if it is on the curve, otherwise returns none.
-    pub fn make_point(&self, x: Option<Integer>, y: Option<Integer>) -> Result<Point,String> {
+    pub fn make_point(&self, x: Option<Integer>, y: Option<Integer>) -> Result<Point<F>,String> {
         match (x, y) {
             (Some(x_raw), Some(y_raw)) => {
                 self.make_point_integral(x_raw, y_raw)
@@ -54,7 +260,7 @@ impl FiniteEllipticCurve {
     }
 
     /// Makes a new point where the values will never be infinite
-    pub fn make_point_integral(&self, x: Integer, y: Integer) -> Result<Point,String> {
+    pub fn make_point_integral(&self, x: Integer, y: Integer) -> Result<Point<F>,String> {
         let fx = self.make_element(x);
         let fy = self.make_element(y);
         if self.on_curve(&fx, &fy) {
@@ -69,27 +275,27 @@ impl FiniteEllipticCurve {
 ///
 /// Infinite values are represented by x or y or both being None.
 #[derive(Clone, Debug, PartialEq)]
-pub struct Point {
-    pub x: Option<FieldElement>,
-    pub y: Option<FieldElement>,
-    pub curve: FiniteEllipticCurve
+pub struct Point<F: Field = FieldElement> {
+    pub x: Option<F>,
+    pub y: Option<F>,
+    pub curve: FiniteEllipticCurve<F>
 }
 
-impl Point {
+impl<F: Field> Point<F> {
     /// Returns a new point wrapping the given x and y coordinates
-    pub fn new(x: Option<FieldElement>,
-               y: Option<FieldElement>,
-               curve: &FiniteEllipticCurve) -> Point {
+    pub fn new(x: Option<F>,
+               y: Option<F>,
+               curve: &FiniteEllipticCurve<F>) -> Point<F> {
         Point { x: x, y: y, curve: curve.clone() }
     }
 
     /// Returns the point at infinity
-    pub fn infinity(curve: &FiniteEllipticCurve) -> Point {
+    pub fn infinity(curve: &FiniteEllipticCurve<F>) -> Point<F> {
         Point { x: None, y: None, curve: curve.clone() }
     }
 
     /// Returns the additive identity value
-    pub fn identity(curve: &FiniteEllipticCurve) -> Point {
+    pub fn identity(curve: &FiniteEllipticCurve<F>) -> Point<F> {
         Self::infinity(curve)
     }
 
@@ -103,11 +309,114 @@ impl Point {
         self.is_identity()
     }
 
+    /// Returns the order of this point, the smallest n > 0 with n*P = 0.
+    ///
+    /// This adds the point to itself until it reaches the identity, so it
+    /// takes time proportional to the order and is only suitable for the
+    /// small curves used in teaching.
+    pub fn order(&self) -> Integer {
+        let mut order = Integer::from(1);
+        let mut current = self.clone();
+        while !current.is_identity() {
+            current = &current + self;
+            order += 1;
+        }
+        order
+    }
+
+    /// Computes the sum k1*P1 + k2*P2 + ... + kn*Pn of the given terms.
+    ///
+    /// This is faster than multiplying each point individually because the
+    /// doublings are shared between all of the terms. Small inputs use
+    /// Strauss-Shamir interleaving with a small precomputed table per point,
+    /// larger inputs use Pippenger's bucket method. A negative scalar k
+    /// multiplies the negated point by -k, and no terms at all give the
+    /// identity of the curve.
+    pub fn multi_mul(terms: &[(Integer, Point<F>)], curve: &FiniteEllipticCurve<F>) -> Point<F> {
+        let terms: Vec<(Integer, Point<F>)> = terms.iter()
+            .filter(|(k, _)| *k != 0)
+            .map(|(k, point)| if *k < 0 { (Integer::from(-k), point.negated()) } else { (k.clone(), point.clone()) })
+            .collect();
+        let terms: Vec<&(Integer, Point<F>)> = terms.iter().collect();
+
+        if terms.len() > PIPPENGER_THRESHOLD {
+            Self::pippenger_mul(&terms, curve)
+        } else {
+            Self::strauss_mul(&terms, curve)
+        }
+    }
+
+    /// Returns the point with the same x coordinate and negated y coordinate
+    fn negated(&self) -> Point<F> {
+        let y = self.y.as_ref().map(|y| y.zero() - y.clone());
+        Point::new(self.x.clone(), y, &self.curve)
+    }
+
+    /// Strauss-Shamir interleaved multiplication using fixed windows.
+    fn strauss_mul(terms: &[&(Integer, Point<F>)], curve: &FiniteEllipticCurve<F>) -> Point<F> {
+        // Precompute [P, 2P, ..., (2^w - 1)P] for each point
+        let tables: Vec<Vec<Point<F>>> = terms.iter().map(|(_, point)| {
+            let mut table = vec![point.clone()];
+            for i in 1..(1 << STRAUSS_WINDOW_BITS) - 1 {
+                let next = &table[i - 1] + point;
+                table.push(next);
+            }
+            table
+        }).collect();
+
+        let windows = max_window_count(terms, STRAUSS_WINDOW_BITS);
+        let mut result = Point::infinity(curve);
+        for window in (0..windows).rev() {
+            for _ in 0..STRAUSS_WINDOW_BITS {
+                result = &result + &result;
+            }
+            for (&(k, _), table) in terms.iter().zip(tables.iter()) {
+                let digit = window_value(k, window * STRAUSS_WINDOW_BITS, STRAUSS_WINDOW_BITS);
+                if digit > 0 {
+                    result = &result + &table[digit - 1];
+                }
+            }
+        }
+        result
+    }
+
+    /// Pippenger's bucket method for multiplication over many terms.
+    fn pippenger_mul(terms: &[&(Integer, Point<F>)], curve: &FiniteEllipticCurve<F>) -> Point<F> {
+        // Roughly log2(n) bit windows balance bucket count against additions
+        let width = (usize::BITS - terms.len().leading_zeros()).max(1);
+        let windows = max_window_count(terms, width);
+        let mut result = Point::infinity(curve);
+
+        for window in (0..windows).rev() {
+            for _ in 0..width {
+                result = &result + &result;
+            }
+
+            let mut buckets = vec![Point::infinity(curve); (1 << width) - 1];
+            for &(k, point) in terms {
+                let digit = window_value(k, window * width, width);
+                if digit > 0 {
+                    buckets[digit - 1] = &buckets[digit - 1] + point;
+                }
+            }
+
+            // Sum d * bucket[d] using running sums from the highest bucket
+            let mut running = Point::infinity(curve);
+            let mut window_sum = Point::infinity(curve);
+            for bucket in buckets.iter().rev() {
+                running = &running + bucket;
+                window_sum = &window_sum + &running;
+            }
+            result = &result + &window_sum;
+        }
+        result
+    }
+
     /// Returns the slope of the line between two points
-    pub fn slope(&self, other: &Point) -> Option<FieldElement> {
+    pub fn slope(&self, other: &Point<F>) -> Option<F> {
         match (self.x.as_ref(), self.y.as_ref(), other.x.as_ref(), other.y.as_ref()) {
             (Some(x1), Some(y1), Some(x2), Some(y2)) => {
-                Some((y2 - y1) / (x2 - x1))
+                Some((y2.clone() - y1.clone()) / (x2.clone() - x1.clone()))
             },
             _ => None
                 
@@ -115,20 +424,150 @@ impl Point {
     }
 
     /// Returns the slope of the tangent line at a given point
-    pub fn tangent_slope(&self) -> Option<FieldElement> {
+    pub fn tangent_slope(&self) -> Option<F> {
         match (self.x.as_ref(), self.y.as_ref()) {
             (Some(x), Some(y)) => {
-                Some((3 * &x.pow(&Integer::from(2)) + &self.curve.curve.a) / (2 * y))
+                let x_squared = x.clone() * x.clone();
+                let numerator = x_squared.clone() + x_squared.clone() + x_squared + self.curve.a.clone();
+                Some(numerator / (y.clone() + y.clone()))
             },
             _ => None
         }
     }
 }
 
-impl<'a, 'b> Add<&'b Point> for &'a Point {
-    type Output = Point;
+impl Point {
+    /// Returns the order of this point given some multiple of it.
+    ///
+    /// Each prime factor of the multiple is divided out for as long as the
+    /// result still takes the point to the identity.
+    fn order_dividing(&self, multiple: &Integer) -> Integer {
+        let mut order = multiple.clone();
+        for factor in prime_factors(multiple) {
+            while order.is_divisible(&factor) &&
+                (self * Integer::from(&order / &factor)).is_identity()
+            {
+                order /= &factor;
+            }
+        }
+        order
+    }
+
+    /// Returns the order of this point, which must have a multiple in
+    /// [low, low + 2 * bound].
+    fn order_in_range(&self, low: &Integer, bound: &Integer) -> Result<Integer, String> {
+        let multiple = self.multiple_in_range(low, bound)
+            .ok_or_else(|| "Field is too large to count points".to_string())?;
+        Ok(self.order_dividing(&multiple))
+    }
+
+    /// Finds some n in [low, low + 2 * bound] with nP = 0 by baby-step
+    /// giant-step. Hasse's theorem guarantees such an n on a non-singular
+    /// curve when the interval is centered on p + 1. Returns None if there is
+    /// none or the interval is too wide to search.
+    fn multiple_in_range(&self, low: &Integer, bound: &Integer) -> Option<Integer> {
+        let width: Integer = Integer::from(bound * 2) + 1;
+        let steps = (width.sqrt() + 1u32).to_usize().filter(|&steps| steps <= BSGS_MAX_STEPS)?;
+
+        // Baby steps jP for 0 <= j < m
+        let mut baby_steps = HashMap::new();
+        let mut current = Point::identity(&self.curve);
+        for j in 0..steps {
+            baby_steps.entry(point_key(&current, false)).or_insert(j);
+            current = &current + self;
+        }
+
+        // Giant steps (low + im)P, looking for one equal to -jP
+        let giant_step = current;
+        let mut current = self * low;
+        for i in 0..steps {
+            if let Some(&j) = baby_steps.get(&point_key(&current, true)) {
+                return Some(Integer::from(i * steps + j) + low);
+            }
+            current = &current + &giant_step;
+        }
+        None
+    }
+}
+
+/// Returns the distinct prime factors of n.
+///
+/// Small factors are found by trial division and the rest by Pollard's rho,
+/// which takes around n^(1/4) steps in the worst case.
+fn prime_factors(n: &Integer) -> Vec<Integer> {
+    let mut factors = Vec::new();
+    let mut remaining = n.clone();
+    for factor in 2..TRIAL_DIVISION_LIMIT {
+        if remaining.is_divisible_u(factor) {
+            factors.push(Integer::from(factor));
+            while remaining.is_divisible_u(factor) {
+                remaining /= factor;
+            }
+        }
+    }
+
+    let mut composites = vec![remaining];
+    while let Some(m) = composites.pop() {
+        if m == 1 {
+            continue;
+        }
+        if m.is_probably_prime(30) != IsPrime::No {
+            factors.push(m);
+        } else {
+            let divisor = pollard_rho(&m);
+            composites.push(Integer::from(&m / &divisor));
+            composites.push(divisor);
+        }
+    }
+    factors.sort();
+    factors.dedup();
+    factors
+}
+
+/// Finds a non-trivial divisor of a composite number with Pollard's rho
+/// method, using Floyd's cycle detection on x -> x^2 + c.
+fn pollard_rho(n: &Integer) -> Integer {
+    let mut c = Integer::from(1);
+    loop {
+        let step = |x: &Integer| Integer::from(x * x + &c) % n;
+        let (mut tortoise, mut hare) = (Integer::from(2), Integer::from(2));
+        let mut divisor = Integer::from(1);
+        while divisor == 1 {
+            tortoise = step(&tortoise);
+            hare = step(&step(&hare));
+            divisor = Integer::from(&tortoise - &hare).abs().gcd(n);
+        }
+        if divisor != *n {
+            return divisor;
+        }
+        c += 1;
+    }
+}
 
-    fn add(self, other: &'b Point) -> Point {
+/// Returns the coordinates of a point, or of its negation, as hashable
+/// integers.
+pub(crate) fn point_key(point: &Point, negate: bool) -> (Option<Integer>, Option<Integer>) {
+    let y = point.y.as_ref().map(|y| if negate { (-y).value } else { y.value.clone() });
+    (point.x.as_ref().map(|x| x.value.clone()), y)
+}
+
+/// Number of windows of the given width needed to cover the largest scalar.
+fn max_window_count<F: Field>(terms: &[&(Integer, Point<F>)], width: u32) -> u32 {
+    let bits = terms.iter().map(|(k, _)| k.significant_bits()).max().unwrap_or(0);
+    bits.div_ceil(width)
+}
+
+/// Returns the value of `width` bits of the scalar starting at bit `start`.
+fn window_value(scalar: &Integer, start: u32, width: u32) -> usize {
+    (start..start + width).rev().fold(0, |value, bit| {
+        (value << 1) | (scalar.get_bit(bit) as usize)
+    })
+}
+
+impl<F: Field> Add<&Point<F>> for &Point<F> {
+    type Output = Point<F>;
+
+    fn add(self, other: &Point<F>) -> Point<F> {
         if other.is_identity() {
             return self.clone()
         } else if self.is_identity() {
@@ -142,16 +581,16 @@ impl<'a, 'b> Add<&'b Point> for &'a Point {
                         Point::infinity(&self.curve)
                     } else {
                         let slope = self.tangent_slope().unwrap();
-                        let x3 = &slope.pow(&Integer::from(2)) - 2 * x1;
-                        let y = &slope * (x1 - &x3) - y1;
+                        let x3 = slope.clone() * slope.clone() - x1.clone() - x1.clone();
+                        let y = slope * (x1.clone() - x3.clone()) - y1.clone();
                         Point::new(Some(x3), Some(y), &self.curve)
                     }
                 } else if x1 == x2 {
                     Point::infinity(&self.curve)
                 } else {
                     let slope = self.slope(&other).unwrap();
-                    let x3 = &slope.pow(&Integer::from(2)) - x1 - x2;
-                    let y = &slope * (x1 - &x3) - y1;
+                    let x3 = slope.clone() * slope.clone() - x1.clone() - x2.clone();
+                    let y = slope * (x1.clone() - x3.clone()) - y1.clone();
                     Point::new(Some(x3), Some(y), &self.curve)
                 }
             },
@@ -162,34 +601,34 @@ impl<'a, 'b> Add<&'b Point> for &'a Point {
     }
 }
 
-impl<'a> Add<&'a Point> for Point {
-    type Output = Point;
+impl<F: Field> Add<&Point<F>> for Point<F> {
+    type Output = Point<F>;
 
-    fn add(self, other: &'a Point) -> Point {
+    fn add(self, other: &Point<F>) -> Point<F> {
         &self + other
     }
 }
 
-impl<'a> Add<Point> for &'a Point {
-    type Output = Point;
+impl<F: Field> Add<Point<F>> for &Point<F> {
+    type Output = Point<F>;
 
-    fn add(self, other: Point) -> Point {
+    fn add(self, other: Point<F>) -> Point<F> {
         self + &other
     }
 }
 
-impl Add for Point {
-    type Output = Point;
+impl<F: Field> Add for Point<F> {
+    type Output = Point<F>;
 
-    fn add(self, other: Point) -> Point {
+    fn add(self, other: Point<F>) -> Point<F> {
         &self + &other
     }
 }
 
-impl<'a, 'b> Mul<&'b Integer> for &'a Point {
-    type Output = Point;
+impl<F: Field> Mul<&Integer> for &Point<F> {
+    type Output = Point<F>;
 
-    fn mul(self, coefficient: &'b Integer) -> Point {
+    fn mul(self, coefficient: &Integer) -> Point<F> {
         let mut coeff = coefficient.clone();
         let mut current = self.clone();
         let mut result = Point::infinity(&self.curve);
@@ -204,50 +643,50 @@ impl<'a, 'b> Mul<&'b Integer> for &'a Point {
     }
 }
 
-impl<'a> Mul<&'a Integer> for Point {
-    type Output = Point;
+impl<F: Field> Mul<&Integer> for Point<F> {
+    type Output = Point<F>;
 
-    fn mul(self, coefficient: &'a Integer) -> Point {
+    fn mul(self, coefficient: &Integer) -> Point<F> {
         &self * coefficient
     }
 }
 
-impl<'a> Mul<Integer> for &'a Point {
-    type Output = Point;
+impl<F: Field> Mul<Integer> for &Point<F> {
+    type Output = Point<F>;
 
-    fn mul(self, coefficient: Integer) -> Point {
+    fn mul(self, coefficient: Integer) -> Point<F> {
         self * &coefficient
     }
 }
 
-impl<'a, 'b> Mul<&'b Point> for &'a Integer {
-    type Output = Point;
+impl<F: Field> Mul<&Point<F>> for &Integer {
+    type Output = Point<F>;
 
-    fn mul(self, coefficient: &'b Point) -> Point {
+    fn mul(self, coefficient: &Point<F>) -> Point<F> {
         coefficient * self
     }
 }
 
-impl Mul<Integer> for Point {
-    type Output = Point;
+impl<F: Field> Mul<Integer> for Point<F> {
+    type Output = Point<F>;
 
-    fn mul(self, coefficient: Integer) -> Point {
+    fn mul(self, coefficient: Integer) -> Point<F> {
         &self * &coefficient
     }
 }
 
-impl<'a, 'b> Mul<&'b FieldElement> for &'a Point {
-    type Output = Point;
+impl<F: Field> Mul<&FieldElement> for &Point<F> {
+    type Output = Point<F>;
 
-    fn mul(self, coefficient: &'b FieldElement) -> Point {
+    fn mul(self, coefficient: &FieldElement) -> Point<F> {
         self * &coefficient.value
     }
 }
 
-impl<'a, 'b> Mul<&'b Point> for &'a FieldElement {
-    type Output = Point;
+impl<F: Field> Mul<&Point<F>> for &FieldElement {
+    type Output = Point<F>;
 
-    fn mul(self, coefficient: &'b Point) -> Point {
+    fn mul(self, coefficient: &Point<F>) -> Point<F> {
         coefficient * &self.value
     }
 }
@@ -265,7 +704,7 @@ impl fmt::Display for Point {
 
 #[test]
 fn test_finiteellipticcurve_on_curve() {
-    let gf_223 = Rc::new(GaloisField::new(Integer::from(223)));
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
     let ec = EllipticCurve::new(Integer::from(0), Integer::from(7));
     let fec = FiniteEllipticCurve::new(ec, &gf_223.clone());
 
@@ -285,9 +724,70 @@ fn test_finiteellipticcurve_on_curve() {
     }
 }
 
+#[test]
+fn test_finiteellipticcurve_count_points() {
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let fec = FiniteEllipticCurve::new(EllipticCurve::new(Integer::from(0), Integer::from(7)), &gf_223);
+    let points = fec.points();
+    assert_eq!(points.len(), 252);
+    assert_eq!(fec.count_points(), Ok(Integer::from(252)));
+    assert!(points.iter().skip(1).all(|p| fec.on_curve(p.x.as_ref().unwrap(), p.y.as_ref().unwrap())));
+
+    // This group is Z/6 x Z/42, so it has no generators
+    assert!(fec.generators().unwrap().is_empty());
+
+    // y^2 = x^3 + 2x + 2 over F_17 has a prime number of points, so every
+    // point except the identity generates the group
+    let gf_17 = Arc::new(GaloisField::new(Integer::from(17)));
+    let fec = FiniteEllipticCurve::new(EllipticCurve::new(Integer::from(2), Integer::from(2)), &gf_17);
+    assert_eq!(fec.count_points(), Ok(Integer::from(19)));
+    assert_eq!(fec.generators().unwrap().len(), 18);
+    assert_eq!(fec.is_generator(&fec.make_point_integral(Integer::from(5), Integer::from(1)).unwrap()), Ok(true));
+    assert_eq!(fec.is_generator(&Point::identity(&fec)), Ok(false));
+
+    for &(prime, a, b, count) in &[(10007, 0, 7, 10008), (10007, 3, 5, 10125), (223, 0, 7, 252)] {
+        let field = Arc::new(GaloisField::new(Integer::from(prime)));
+        let fec = FiniteEllipticCurve::new(EllipticCurve::new(Integer::from(a), Integer::from(b)), &field);
+        assert_eq!(fec.count_points_naive(), Ok(Integer::from(count)));
+        assert_eq!(fec.count_points_bsgs(), Ok(Integer::from(count)));
+    }
+
+    // A 40-bit field is counted quickly. The count kills every point, and the
+    // twist has the remaining 2p + 2 - N points
+    let prime = Integer::from(1099511627689u64);
+    let field = Arc::new(GaloisField::new(prime.clone()));
+    let fec = FiniteEllipticCurve::new(EllipticCurve::new(Integer::from(3), Integer::from(5)), &field);
+    let count = fec.count_points().unwrap();
+    let hasse_bound = Integer::from(&prime * 4).sqrt();
+    let distance: Integer = Integer::from(&count - &prime) - 1;
+    assert!(distance.abs() <= hasse_bound);
+    let mut x = Integer::from(0);
+    for _ in 0..4 {
+        assert!((&fec.next_point(&mut x).unwrap() * &count).is_identity());
+    }
+    assert_eq!(fec.quadratic_twist().count_points_bsgs(), Ok((prime + 1) * 2 - count));
+
+    // Singular curves, including every curve over F_2, have no point count
+    for &(prime, a, b) in &[(223, 0, 0), (10007, 10004, 2), (2, 1, 1)] {
+        let field = Arc::new(GaloisField::new(Integer::from(prime)));
+        let fec = FiniteEllipticCurve::new(EllipticCurve::new(Integer::from(a), Integer::from(b)), &field);
+        assert!(fec.is_singular());
+        assert!(fec.count_points_naive().is_err());
+        assert!(fec.count_points_bsgs().is_err());
+        assert!(fec.generators().is_err());
+    }
+
+    // The secp256k1 field is far too large to count
+    let secp256k1 = FiniteEllipticCurve::new(
+        EllipticCurve::new(Integer::from(0), Integer::from(7)),
+        &Arc::new(GaloisField::new((Integer::from(1) << 256) - (Integer::from(1) << 32) - 977)));
+    assert!(!secp256k1.is_singular());
+    assert!(secp256k1.count_points().is_err());
+}
+
 #[test]
 fn test_point_additive_identity() {
-    let gf_223 = Rc::new(GaloisField::new(Integer::from(223)));
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
     let ec = EllipticCurve::new(Integer::from(0), Integer::from(7));
     let fec = FiniteEllipticCurve::new(ec, &gf_223.clone());
     let identity = Point::identity(&fec);
@@ -301,7 +801,7 @@ fn test_point_additive_identity() {
 
 #[test]
 fn test_point_add() {
-    let gf_223 = Rc::new(GaloisField::new(Integer::from(223)));
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
     let ec = EllipticCurve::new(Integer::from(0), Integer::from(7));
     let fec = FiniteEllipticCurve::new(ec, &gf_223.clone());
 
@@ -320,6 +820,95 @@ fn test_point_add() {
     }
 }
 
+#[test]
+fn test_point_order() {
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let ec = EllipticCurve::new(Integer::from(0), Integer::from(7));
+    let fec = FiniteEllipticCurve::new(ec, &gf_223);
+
+    // The curve has 252 = 7 * 36 points, and (15, 86) generates the subgroup
+    // of order 7
+    let generator = fec.make_point_integral(Integer::from(15), Integer::from(86)).unwrap();
+    assert_eq!(generator.order(), 7);
+    assert_eq!(Point::identity(&fec).order(), 1);
+
+    let outside = fec.make_point_integral(Integer::from(47), Integer::from(71)).unwrap();
+    assert_eq!(outside.order(), 21);
+    assert_eq!((&outside * Integer::from(36)).order(), 7);
+}
+
+#[test]
+fn test_point_multi_mul() {
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let ec = EllipticCurve::new(Integer::from(0), Integer::from(7));
+    let fec = FiniteEllipticCurve::new(ec, &gf_223.clone());
+
+    let points = [(192, 105), (17, 56), (1, 193), (47, 71), (143, 98)];
+    let terms: Vec<(Integer, Point)> = points.iter().enumerate().map(|(i, &(x, y))| {
+        let pt = fec.make_point_integral(Integer::from(x), Integer::from(y)).unwrap();
+        (Integer::from(37 * i + 5), pt)
+    }).collect();
+
+    // Few terms go through Strauss-Shamir
+    assert_eq!(Point::multi_mul(&[], &fec), Point::infinity(&fec));
+    for count in [1, 2, terms.len()] {
+        let expected = terms[..count].iter().fold(
+            Point::infinity(&fec), |acc, (k, pt)| acc + pt * k
+        );
+        assert_eq!(Point::multi_mul(&terms[..count], &fec), expected);
+    }
+
+    // More than PIPPENGER_THRESHOLD terms go through Pippenger
+    let many: Vec<(Integer, Point)> =
+        terms.iter().cycle().take(3 * PIPPENGER_THRESHOLD).cloned().collect();
+    let expected = many.iter().fold(
+        Point::infinity(&fec), |acc, (k, pt)| acc + pt * k
+    );
+    assert_eq!(Point::multi_mul(&many, &fec), expected);
+
+    // Zero scalars contribute nothing and negative ones subtract
+    let mut signed = terms.clone();
+    signed[0].0 = Integer::from(0);
+    signed[1].0 = Integer::from(-3);
+    let expected = terms[2..].iter().fold(
+        Point::infinity(&fec), |acc, (k, pt)| acc + pt * k
+    );
+    let three_p = &terms[1].1 * Integer::from(3);
+    assert_eq!(Point::multi_mul(&signed, &fec) + three_p, expected);
+}
+
+#[test]
+fn test_point_generic_field() {
+    use programmingbitcoin::secp256k1field::*;
+    use rug::ops::*;
+
+    let gx = Integer::from_str_radix(
+        "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798", 16
+    ).unwrap();
+    let gy = Integer::from_str_radix(
+        "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8", 16
+    ).unwrap();
+    let p = Integer::from(2).pow(256) - Integer::from(2).pow(32) - Integer::from(977);
+
+    let field = Arc::new(GaloisField::new(p));
+    let curve = EllipticCurve::new(Integer::from(0), Integer::from(7));
+    let secp256k1 = FiniteEllipticCurve::new(curve.clone(), &field);
+    let fixed_secp256k1 = FiniteEllipticCurve::<Secp256k1FieldElement>::with_field(curve, &());
+
+    let generator = secp256k1.make_point_integral(gx.clone(), gy.clone()).unwrap();
+    let fixed_generator = fixed_secp256k1.make_point_integral(gx, gy).unwrap();
+    assert!(fixed_secp256k1.make_point_integral(Integer::from(1), Integer::from(1)).is_err());
+
+    for k in ["2", "3", "112233445566778899", "57896044618658097711785492504343953926418782139537452191302581570759080747168"] {
+        let k = k.parse::<Integer>().unwrap();
+        let expected = &generator * &k;
+        let result = &fixed_generator * &k;
+
+        assert_eq!(result.x.unwrap().to_integer(), expected.x.unwrap().value);
+        assert_eq!(result.y.unwrap().to_integer(), expected.y.unwrap().value);
+    }
+}
+
 #[test]
 fn test_point_mul() {
     use rug::ops::*;
@@ -335,7 +924,7 @@ fn test_point_mul() {
     ).unwrap();
     let p = Integer::from(2).pow(256) - Integer::from(2).pow(32) - Integer::from(977);
 
-    let field = Rc::new(GaloisField::new(p));
+    let field = Arc::new(GaloisField::new(p));
     let curve = EllipticCurve::new(Integer::from(0), Integer::from(7));
     let secp256k1 = FiniteEllipticCurve::new(curve, &field.clone());
     let generator_point = secp256k1.make_point_integral(gx, gy).unwrap();
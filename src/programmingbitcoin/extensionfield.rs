@@ -0,0 +1,426 @@
+//! Prime field extensions F_p^k in a polynomial basis
+//!
+//! An element of F_p^k is a polynomial over F_p of degree less than k, and
+//! arithmetic is carried out modulo a monic irreducible polynomial of degree
+//! k. Extensions such as F_p^2 and F_p^12 are used by pairing-friendly
+//! curves.
+use rug::Integer;
+use rug::ops::Pow;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::sync::Arc;
+
+use programmingbitcoin::finitefield::*;
+use programmingbitcoin::polynomial::Polynomial;
+
+/// An extension of a prime field by a monic irreducible polynomial.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtensionField {
+    pub base: Arc<GaloisField>,
+    /// Coefficients of the modulus from lowest to highest degree, ending in 1
+    pub modulus: Vec<Integer>
+}
+
+impl ExtensionField {
+    /// Create a new extension of the base field.
+    ///
+    /// The modulus is given from lowest to highest degree without its leading
+    /// coefficient, so x^2 + 1 is given as [1, 0]. Returns an error if the
+    /// resulting polynomial is not irreducible over the base field.
+    pub fn new(base: &Arc<GaloisField>, modulus: Vec<Integer>) -> Result<ExtensionField, String> {
+        if modulus.is_empty() {
+            return Err("Extension degree must be at least one".to_string());
+        }
+
+        let mut monic: Vec<Integer> = modulus.into_iter().map(|c| base.value_of(c)).collect();
+        monic.push(Integer::from(1));
+        let field = ExtensionField { base: base.clone(), modulus: monic };
+        if field.is_irreducible() {
+            Ok(field)
+        } else {
+            Err("Extension modulus is not irreducible".to_string())
+        }
+    }
+
+    /// Returns the degree k of the extension
+    pub fn degree(&self) -> usize {
+        self.modulus.len() - 1
+    }
+
+    /// Returns the number of elements in the field, p^k
+    pub fn order(&self) -> Integer {
+        self.base.prime.clone().pow(self.degree() as u32)
+    }
+
+    /// Returns the modulus as a polynomial over the base field
+    fn modulus_polynomial(&self) -> Polynomial {
+        Polynomial::from_integers(self.modulus.clone(), &self.base)
+    }
+
+    /// Rabin's irreducibility test.
+    ///
+    /// A polynomial f of degree k is irreducible exactly when f divides
+    /// x^(p^k) - x, and x^(p^(k/q)) - x shares no factor with f for every
+    /// prime q dividing k.
+    fn is_irreducible(&self) -> bool {
+        let k = self.degree();
+        let field = Arc::new(self.clone());
+        let x = ExtensionFieldElement::x(&field);
+
+        for q in prime_factors(k) {
+            let difference = x.frobenius(k / q) - x.clone();
+            let common = self.modulus_polynomial().gcd(&difference.to_polynomial());
+            if common.degree() != Some(0) {
+                return false;
+            }
+        }
+        x.frobenius(k) == x
+    }
+}
+
+/// An element of an extension field, stored as the coefficients of a
+/// polynomial from lowest to highest degree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtensionFieldElement {
+    pub coefficients: Vec<Integer>,
+    pub field: Arc<ExtensionField>
+}
+
+impl ExtensionFieldElement {
+    /// Create a new element from polynomial coefficients, lowest degree first.
+    ///
+    /// The polynomial is reduced by the field modulus, so any number of
+    /// coefficients may be given.
+    pub fn new(coefficients: Vec<Integer>, field: &Arc<ExtensionField>) -> ExtensionFieldElement {
+        let polynomial = Polynomial::from_integers(coefficients, &field.base);
+        Self::from_polynomial(&polynomial, field)
+    }
+
+    /// Returns the element x, the root of the modulus that generates the field
+    pub fn x(field: &Arc<ExtensionField>) -> ExtensionFieldElement {
+        Self::new(vec![Integer::from(0), Integer::from(1)], field)
+    }
+
+    /// Applies the Frobenius map a -> a^p the given number of times.
+    pub fn frobenius(&self, power: usize) -> ExtensionFieldElement {
+        let mut result = self.clone();
+        for _ in 0..power {
+            result = Field::pow(&result, &self.field.base.prime);
+        }
+        result
+    }
+
+    /// Returns this element as a polynomial over the base field
+    pub fn to_polynomial(&self) -> Polynomial {
+        Polynomial::from_integers(self.coefficients.clone(), &self.field.base)
+    }
+
+    /// Create a new element from a polynomial over the base field, reducing
+    /// it by the field modulus.
+    pub fn from_polynomial(polynomial: &Polynomial, field: &Arc<ExtensionField>) -> ExtensionFieldElement {
+        ExtensionFieldElement { coefficients: reduce(polynomial, field), field: field.clone() }
+    }
+}
+
+/// Reduces a polynomial by the field modulus, padding the coefficients of
+/// the remainder out to the degree of the field.
+fn reduce(polynomial: &Polynomial, field: &ExtensionField) -> Vec<Integer> {
+    let (_, remainder) = polynomial.div_rem(&field.modulus_polynomial()).expect("modulus is not zero");
+    let mut coefficients: Vec<Integer> = remainder.coefficients.into_iter().map(|c| c.value).collect();
+    coefficients.resize(field.degree(), Integer::from(0));
+    coefficients
+}
+
+/// Returns the distinct prime factors of n
+fn prime_factors(mut n: usize) -> Vec<usize> {
+    let mut factors = Vec::new();
+    let mut d = 2;
+    while d * d <= n {
+        if n.is_multiple_of(d) {
+            factors.push(d);
+            while n.is_multiple_of(d) {
+                n /= d;
+            }
+        }
+        d += 1;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+impl<'b> Add<&'b ExtensionFieldElement> for &ExtensionFieldElement {
+    type Output = ExtensionFieldElement;
+
+    fn add(self, other: &'b ExtensionFieldElement) -> ExtensionFieldElement {
+        assert!(self.field == other.field, "Extension field elements belong to different fields");
+        let coefficients = self.coefficients.iter().zip(other.coefficients.iter())
+            .map(|(a, b)| self.field.base.value_of(Integer::from(a + b)))
+            .collect();
+        ExtensionFieldElement { coefficients, field: self.field.clone() }
+    }
+}
+
+impl<'b> Sub<&'b ExtensionFieldElement> for &ExtensionFieldElement {
+    type Output = ExtensionFieldElement;
+
+    fn sub(self, other: &'b ExtensionFieldElement) -> ExtensionFieldElement {
+        self + &(-other)
+    }
+}
+
+impl<'b> Mul<&'b ExtensionFieldElement> for &ExtensionFieldElement {
+    type Output = ExtensionFieldElement;
+
+    fn mul(self, other: &'b ExtensionFieldElement) -> ExtensionFieldElement {
+        assert!(self.field == other.field, "Extension field elements belong to different fields");
+        let product = &self.to_polynomial() * &other.to_polynomial();
+        ExtensionFieldElement::from_polynomial(&product, &self.field)
+    }
+}
+
+impl<'b> Div<&'b ExtensionFieldElement> for &ExtensionFieldElement {
+    type Output = ExtensionFieldElement;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: &'b ExtensionFieldElement) -> ExtensionFieldElement {
+        self * &other.inv().expect("division by zero in extension field")
+    }
+}
+
+impl Neg for &ExtensionFieldElement {
+    type Output = ExtensionFieldElement;
+
+    fn neg(self) -> ExtensionFieldElement {
+        let coefficients = self.coefficients.iter()
+            .map(|c| self.field.base.value_of(Integer::from(-c)))
+            .collect();
+        ExtensionFieldElement { coefficients, field: self.field.clone() }
+    }
+}
+
+impl Neg for ExtensionFieldElement {
+    type Output = ExtensionFieldElement;
+
+    fn neg(self) -> ExtensionFieldElement {
+        -&self
+    }
+}
+
+macro_rules! owned_extension_op {
+    ($($op:ident, $method:ident);*) => {$(
+        impl $op for ExtensionFieldElement {
+            type Output = ExtensionFieldElement;
+
+            fn $method(self, other: ExtensionFieldElement) -> ExtensionFieldElement {
+                (&self).$method(&other)
+            }
+        }
+    )*}
+}
+
+owned_extension_op!(Add, add; Sub, sub; Mul, mul; Div, div);
+
+impl Field for ExtensionFieldElement {
+    type Context = Arc<ExtensionField>;
+
+    /// Embeds an integer as a constant polynomial
+    fn make_element(field: &Arc<ExtensionField>, value: Integer) -> ExtensionFieldElement {
+        ExtensionFieldElement::new(vec![value], field)
+    }
+
+    fn zero(&self) -> ExtensionFieldElement {
+        ExtensionFieldElement::new(Vec::new(), &self.field)
+    }
+
+    fn one(&self) -> ExtensionFieldElement {
+        ExtensionFieldElement::new(vec![Integer::from(1)], &self.field)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.coefficients.iter().all(|c| *c == 0)
+    }
+
+    /// Inverts using the extended Euclidean algorithm over F_p[x].
+    fn inv(&self) -> Option<ExtensionFieldElement> {
+        let mut r0 = self.field.modulus_polynomial();
+        let mut r1 = self.to_polynomial();
+        let mut s0 = Polynomial::zero(&self.field.base);
+        let mut s1 = Polynomial::constant(FieldElement::new(Integer::from(1), &self.field.base));
+
+        while !r1.is_zero() {
+            let (quotient, remainder) = r0.div_rem(&r1).expect("divisor is not zero");
+            let s2 = &s0 - &(&quotient * &s1);
+            r0 = r1;
+            r1 = remainder;
+            s0 = s1;
+            s1 = s2;
+        }
+
+        // r0 is the gcd, which is a non-zero constant for invertible elements
+        if r0.degree() != Some(0) {
+            return None;
+        }
+        let scale = Field::inv(&r0.coefficients[0])?;
+        Some(ExtensionFieldElement::from_polynomial(&s0.scale(&scale), &self.field))
+    }
+
+    fn pow(&self, exponent: &Integer) -> ExtensionFieldElement {
+        let mut result = self.one();
+        for bit in (0..exponent.significant_bits()).rev() {
+            result = &result * &result;
+            if exponent.get_bit(bit) {
+                result = &result * self;
+            }
+        }
+        result
+    }
+
+    /// Tonelli-Shanks over the whole extension field.
+    fn sqrt(&self) -> Option<ExtensionFieldElement> {
+        if self.is_zero() {
+            return Some(self.clone());
+        }
+        let one = self.one();
+        let order_minus_one: Integer = self.field.order() - 1;
+        let euler_exponent = Integer::from(&order_minus_one >> 1);
+        if self.pow(&euler_exponent) != one {
+            return None;
+        }
+
+        let mut q = order_minus_one;
+        let mut s = 0;
+        while q.is_even() {
+            q >>= 1;
+            s += 1;
+        }
+
+        // Search for a quadratic non-residue of the form x + c. Constants are
+        // always squares in an extension of even degree, so they cannot be used.
+        let mut offset = 0u32;
+        let z = loop {
+            let z = ExtensionFieldElement::new(vec![Integer::from(offset), Integer::from(1)], &self.field);
+            if !z.is_zero() && z.pow(&euler_exponent) != one {
+                break z;
+            }
+            offset += 1;
+        };
+
+        let mut m = s;
+        let mut c = z.pow(&q);
+        let mut t = self.pow(&q);
+        let mut r = self.pow(&Integer::from(&q + 1).div_exact(&Integer::from(2)));
+        while t != one {
+            let mut i = 0;
+            let mut t_squared = t.clone();
+            while t_squared != one {
+                t_squared = &t_squared * &t_squared;
+                i += 1;
+            }
+
+            let b = c.pow(&(Integer::from(1) << (m - i - 1) as u32));
+            m = i;
+            c = &b * &b;
+            t = &t * &c;
+            r = &r * &b;
+        }
+        Some(r)
+    }
+}
+
+/// Formats an element as a polynomial in x, for example `3x + 5`.
+impl fmt::Display for ExtensionFieldElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let terms: Vec<String> = self.coefficients.iter().enumerate().rev()
+            .filter(|&(_, c)| *c != 0)
+            .map(|(degree, c)| match degree {
+                0 => format!("{}", c),
+                1 => format!("{}x", c),
+                _ => format!("{}x^{}", c, degree)
+            })
+            .collect();
+
+        if terms.is_empty() {
+            write!(f, "0")
+        } else {
+            write!(f, "{}", terms.join(" + "))
+        }
+    }
+}
+
+#[test]
+fn test_extension_field_arithmetic() {
+    // The BN254 base field and the F_p^2 and F_p^12 towers used by its pairing
+    let prime = Integer::from_str_radix(
+        "30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47", 16
+    ).unwrap();
+    let base = Arc::new(GaloisField::new(prime.clone()));
+    let fp2 = Arc::new(ExtensionField::new(&base, vec![Integer::from(1), Integer::from(0)]).unwrap());
+    let mut fp12_modulus = vec![Integer::from(0); 12];
+    fp12_modulus[0] = Integer::from(82);
+    fp12_modulus[6] = Integer::from(-18);
+    let fp12 = Arc::new(ExtensionField::new(&base, fp12_modulus).unwrap());
+
+    for field in [fp2, fp12] {
+        let k = field.degree();
+        let a = ExtensionFieldElement::new(
+            (0..k).map(|i| Integer::from(i * 7 + 3).pow(20) + Integer::from(&prime / 3)).collect(), &field
+        );
+        let b = ExtensionFieldElement::new(
+            (0..k).map(|i| Integer::from(i + 1) << (i as u32 * 13)).collect(), &field
+        );
+        let one = a.one();
+
+        // i^2 = -1 in F_p^2
+        if k == 2 {
+            let i = ExtensionFieldElement::x(&field);
+            assert_eq!(&i * &i, -one.clone());
+        }
+
+        assert_eq!(&(&a * &b) / &b, a);
+        assert_eq!(&a * &a.inv().unwrap(), one);
+        assert!(a.zero().inv().is_none());
+        assert_eq!(&(&a + &b) - &b, a);
+
+        // Frobenius is a field automorphism of order k that fixes F_p
+        assert_eq!(a.frobenius(k), a);
+        assert!(a.frobenius(1) != a);
+        assert_eq!((&a * &b).frobenius(1), &a.frobenius(1) * &b.frobenius(1));
+        assert_eq!((&a + &b).frobenius(1), &a.frobenius(1) + &b.frobenius(1));
+        let constant = ExtensionFieldElement::make_element(&field, Integer::from(12345));
+        assert_eq!(constant.frobenius(1), constant);
+
+        let square = &a * &a;
+        let root = Field::sqrt(&square).unwrap();
+        assert_eq!(&root * &root, square);
+    }
+}
+
+#[test]
+fn test_extension_field_curve() {
+    use programmingbitcoin::ellipticcurve::*;
+
+    let base = Arc::new(GaloisField::new(Integer::from(19)));
+    assert!(ExtensionField::new(&base, vec![Integer::from(-1), Integer::from(0)]).is_err());
+    let fp2 = Arc::new(ExtensionField::new(&base, vec![Integer::from(1), Integer::from(0)]).unwrap());
+    assert_eq!(fp2.order(), 361);
+
+    // Lift a point onto y^2 = x^3 + 7 over F_19^2 and check the group law
+    let curve = FiniteEllipticCurve::<ExtensionFieldElement>::with_field(
+        EllipticCurve::new(Integer::from(0), Integer::from(7)), &fp2
+    );
+    let x = ExtensionFieldElement::new(vec![Integer::from(2), Integer::from(5)], &fp2);
+    let rhs = &(&(&x * &x) * &x) + &ExtensionFieldElement::make_element(&fp2, Integer::from(7));
+    let y = Field::sqrt(&rhs).unwrap();
+    assert!(curve.on_curve(&x, &y));
+
+    let point = Point::new(Some(x), Some(y), &curve);
+    for k in 2..10 {
+        let multiple = &point * Integer::from(k);
+        if let (Some(mx), Some(my)) = (multiple.x.as_ref(), multiple.y.as_ref()) {
+            assert!(curve.on_curve(mx, my));
+        }
+    }
+    assert_eq!(format!("{}", ExtensionFieldElement::x(&fp2)), "1x");
+}
@@ -1,18 +1,74 @@
 //! Defines data structures and operations on finite fields and their elements
 use rug::Integer;
-use std::ops::{Add, Div, Mul, Sub};
-use std::rc::Rc;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::{Product, Sum};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use programmingbitcoin::montgomery::*;
+
+/// Arithmetic shared by every representation of a field element.
+///
+/// Elements are expected to always be kept reduced, so that `PartialEq`
+/// compares values in the field. Field-specific constants are produced from
+/// an existing element since some representations carry their field with
+/// them at runtime.
+pub trait Field: Clone + PartialEq + fmt::Debug +
+    Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self>
+{
+    /// Describes which field elements belong to, for representations that
+    /// choose their field at runtime.
+    type Context: Clone + PartialEq + fmt::Debug;
+
+    /// Returns the equivalent value of the given integer in the field
+    fn make_element(field: &Self::Context, value: Integer) -> Self;
+
+    /// Returns the additive identity of this element's field
+    fn zero(&self) -> Self;
+
+    /// Returns the multiplicative identity of this element's field
+    fn one(&self) -> Self;
+
+    /// Indicates whether or not this element is the additive identity
+    fn is_zero(&self) -> bool;
+
+    /// Returns the multiplicative inverse of this element, if it has one
+    fn inv(&self) -> Option<Self>;
+
+    /// Raise this element to the given non-negative integer power
+    fn pow(&self, exponent: &Integer) -> Self;
+
+    /// Returns a square root of this element, if it is a quadratic residue
+    fn sqrt(&self) -> Option<Self>;
+}
 
 /// A Galois field with a prime integer modulus.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Fields can optionally carry a Montgomery context, in which case element
+/// exponentiation runs in Montgomery form, converting the base in once and
+/// the result out once. Single products stay on plain division, since a
+/// conversion per multiply costs more than it saves. Fields with the same
+/// prime are equal whichever arithmetic they use.
+#[derive(Clone, Debug)]
 pub struct GaloisField {
-    pub prime: Integer
+    pub prime: Integer,
+    pub montgomery: Option<MontgomeryContext>
 }
 
 impl GaloisField {
     /// Create a new Galois field over the given prime modulus.
     pub fn new(prime: Integer) -> GaloisField {
-        GaloisField { prime: prime }
+        GaloisField { prime, montgomery: None }
+    }
+
+    /// Create a new Galois field over the given odd prime modulus that uses
+    /// Montgomery arithmetic to exponentiate its elements.
+    pub fn new_montgomery(prime: Integer) -> GaloisField {
+        let montgomery = MontgomeryContext::new(&prime);
+        GaloisField { prime, montgomery: Some(montgomery) }
     }
 
     /// Returns the equivalent value of the given integer in this field.
@@ -30,17 +86,25 @@ impl GaloisField {
     }
 }
 
+impl PartialEq for GaloisField {
+    fn eq(&self, other: &GaloisField) -> bool {
+        self.prime == other.prime
+    }
+}
+
+impl Eq for GaloisField {}
+
 /// Represents an element in a Galois Field.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct FieldElement {
     pub value: Integer,
-    pub field: Rc<GaloisField>
+    pub field: Arc<GaloisField>
 }
 
 impl FieldElement {
     /// Initialize a new element. If value > field.prime then its modulus is
     /// taken against field.prime to yield its value within the Galois field.
-    pub fn new(value: Integer, field: &Rc<GaloisField>) -> FieldElement {
+    pub fn new(value: Integer, field: &Arc<GaloisField>) -> FieldElement {
         // Otherwise, simply use the value as is
         FieldElement { value: field.value_of(value), field: field.clone() }
     }
@@ -51,6 +115,14 @@ impl FieldElement {
             return FieldElement::new(Integer::from(0), &self.field.clone());
         }
 
+        if let Some(ref montgomery) = self.field.montgomery {
+            if *exponent >= 0 {
+                let base = montgomery.to_montgomery(&self.value);
+                let result = montgomery.pow(&base, exponent);
+                return FieldElement::new(montgomery.from_montgomery(result), &self.field);
+            }
+        }
+
         if let Some(result) = self.value.pow_mod_ref(exponent, &self.field.prime) {
             FieldElement::new(Integer::from(result), &self.field)
         } else {
@@ -71,214 +143,444 @@ impl FieldElement {
         self.value == 0
     }
 
-    /// Calculates the square root of this field element (only works in
-    /// secp256k1 field)
-    pub fn sqrt(&self) -> Integer {
-        let (power, _) = (self.field.prime.clone() + Integer::from(1)).div_rem_euc(Integer::from(4));
-        self.pow(&power).value
+    /// Adds two elements, returning an error if they are from different fields
+    pub fn checked_add(&self, other: &FieldElement) -> Result<FieldElement, String> {
+        let field = self.common_field(other)?;
+        Ok(add_values(&self.value, &other.value, field))
     }
-}
 
-impl Add for FieldElement {
-    type Output = FieldElement;
-
-    fn add(self, other: FieldElement) -> FieldElement {
-        FieldElement::new(self.value + other.value, &self.field)
+    /// Subtracts two elements, returning an error if they are from different
+    /// fields
+    pub fn checked_sub(&self, other: &FieldElement) -> Result<FieldElement, String> {
+        let field = self.common_field(other)?;
+        Ok(sub_values(&self.value, &other.value, field))
     }
-}
 
-impl<'a, 'b> Add<&'b FieldElement> for &'a FieldElement {
-    type Output = FieldElement;
+    /// Multiplies two elements, returning an error if they are from different
+    /// fields
+    pub fn checked_mul(&self, other: &FieldElement) -> Result<FieldElement, String> {
+        let field = self.common_field(other)?;
+        Ok(mul_values(&self.value, &other.value, field))
+    }
 
-    fn add(self, other: &'b FieldElement) -> FieldElement {
-        FieldElement::new(
-            Integer::from(&self.value + &other.value),
-            &self.field
-        )
+    /// Divides two elements, returning an error if they are from different
+    /// fields or the divisor is zero
+    pub fn checked_div(&self, other: &FieldElement) -> Result<FieldElement, String> {
+        let field = self.common_field(other)?;
+        if other.is_zero() {
+            return Err(format!("Division by zero in field of order {}", field.prime));
+        }
+        Ok(div_values(&self.value, &other.value, field))
     }
-}
 
-impl<'a, 'b> Add<&'b FieldElement> for &'a Integer {
-    type Output = FieldElement;
+    /// Replaces every element of the slice with its multiplicative inverse.
+    ///
+    /// Uses Montgomery's trick so that only a single inversion is needed,
+    /// along with 3(n - 1) multiplications. Returns an error, leaving the
+    /// elements untouched, if any element is zero or the elements are not all
+    /// from the same field.
+    pub fn batch_invert(elements: &mut [FieldElement]) -> Result<(), String> {
+        if let Some(index) = elements.iter().position(|element| element.is_zero()) {
+            return Err(format!("Cannot invert zero element at index {}", index));
+        }
+        if elements.is_empty() {
+            return Ok(());
+        }
+
+        // prefix[i] = elements[0] * ... * elements[i]
+        let mut prefix: Vec<FieldElement> = Vec::with_capacity(elements.len());
+        prefix.push(elements[0].clone());
+        for element in &elements[1..] {
+            let product = prefix[prefix.len() - 1].checked_mul(element)?;
+            prefix.push(product);
+        }
+
+        let total = &prefix[prefix.len() - 1];
+        let mut inverse = Field::inv(total)
+            .ok_or_else(|| format!("{} is not invertible", total))?;
+
+        // Peel one element at a time off the inverse of the running product
+        for i in (1..elements.len()).rev() {
+            let element_inverse = &inverse * &prefix[i - 1];
+            inverse = &inverse * &elements[i];
+            elements[i] = element_inverse;
+        }
+        elements[0] = inverse;
+        Ok(())
+    }
 
-    fn add(self, other: &'b FieldElement) -> FieldElement {
-        FieldElement::new(
-            Integer::from(self + &other.value),
-            &other.field
-        )
+    /// Returns the field shared by both elements.
+    ///
+    /// Mixing elements of different fields, such as a scalar modulo the curve
+    /// order with a coordinate modulo the curve prime, is always a bug.
+    fn common_field(&self, other: &FieldElement) -> Result<&Arc<GaloisField>, String> {
+        if Arc::ptr_eq(&self.field, &other.field) || self.field == other.field {
+            Ok(&self.field)
+        } else {
+            Err(format!(
+                "Field elements belong to different fields (mod {} and mod {})",
+                self.field.prime, other.field.prime
+            ))
+        }
     }
 }
 
-impl<'a> Add<&'a Integer> for FieldElement {
-    type Output = FieldElement;
+impl Field for FieldElement {
+    type Context = Arc<GaloisField>;
 
-    fn add(self, other: &'a Integer) -> FieldElement {
-        FieldElement::new(
-            Integer::from(&self.value + other),
-            &self.field
-        )
+    fn make_element(field: &Arc<GaloisField>, value: Integer) -> FieldElement {
+        FieldElement::new(value, field)
     }
-}
 
-impl Sub for FieldElement {
-    type Output = FieldElement;
+    fn zero(&self) -> FieldElement {
+        FieldElement::new(Integer::from(0), &self.field)
+    }
 
-    fn sub(self, other: FieldElement) -> FieldElement {
-        FieldElement::new(self.value - other.value, &self.field)
+    fn one(&self) -> FieldElement {
+        FieldElement::new(Integer::from(1), &self.field)
     }
-}
 
-impl<'a> Sub<&'a FieldElement> for FieldElement {
-    type Output = FieldElement;
+    fn is_zero(&self) -> bool {
+        FieldElement::is_zero(self)
+    }
 
-    fn sub(self, other: &'a FieldElement) -> FieldElement {
-        FieldElement::new(
-            Integer::from(&self.value - &other.value),
-            &self.field
-        )
+    fn inv(&self) -> Option<FieldElement> {
+        self.value.clone().invert(&self.field.prime).ok()
+            .map(|inverse| FieldElement::new(inverse, &self.field))
     }
-}
 
-impl<'a> Sub<FieldElement> for &'a FieldElement {
-    type Output = FieldElement;
+    fn pow(&self, exponent: &Integer) -> FieldElement {
+        FieldElement::pow(self, exponent)
+    }
+
+    /// Tonelli-Shanks square root, which works for any odd prime modulus.
+    fn sqrt(&self) -> Option<FieldElement> {
+        let prime = &self.field.prime;
+        if self.is_zero() || *prime == 2 {
+            return Some(self.clone());
+        }
+        if self.value.legendre(prime) != 1 {
+            return None;
+        }
+
+        // Write p - 1 = q * 2^s with q odd
+        let mut q = Integer::from(prime - 1);
+        let mut s = 0;
+        while q.is_even() {
+            q >>= 1;
+            s += 1;
+        }
+
+        // Any quadratic non-residue will do as z
+        let mut z = Integer::from(2);
+        while z.legendre(prime) != -1 {
+            z += 1;
+        }
 
-    fn sub(self, other: FieldElement) -> FieldElement {
-        FieldElement::new(
-            Integer::from(&self.value - &other.value),
-            &self.field
-        )
+        let one = Field::one(self);
+        let mut m = s;
+        let mut c = FieldElement::new(z, &self.field).pow(&q);
+        let mut t = self.pow(&q);
+        let mut r = self.pow(&Integer::from(&q + 1).div_exact(&Integer::from(2)));
+        while t != one {
+            // Find the least i such that t^(2^i) = 1
+            let mut i = 0;
+            let mut t_squared = t.clone();
+            while t_squared != one {
+                t_squared = &t_squared * &t_squared;
+                i += 1;
+            }
+
+            let b = c.pow(&(Integer::from(1) << (m - i - 1)));
+            m = i;
+            c = &b * &b;
+            t = &t * &c;
+            r = &r * &b;
+        }
+        Some(r)
     }
 }
 
-impl<'a, 'b> Sub<&'b FieldElement> for &'a FieldElement {
-    type Output = FieldElement;
+/// Integer-like values that can be combined with field elements.
+///
+/// The value is reduced into the field of the element it is combined with.
+trait Operand {
+    fn as_integer(&self) -> Cow<'_, Integer>;
+}
 
-    fn sub(self, other: &'b FieldElement) -> FieldElement {
-        FieldElement::new(
-            Integer::from(&self.value - &other.value),
-            &self.field
-        )
+impl Operand for Integer {
+    fn as_integer(&self) -> Cow<'_, Integer> {
+        Cow::Borrowed(self)
     }
 }
 
-impl Mul for FieldElement {
-    type Output = FieldElement;
+macro_rules! primitive_operand {
+    ($($t:ty),*) => {$(
+        impl Operand for $t {
+            fn as_integer(&self) -> Cow<'_, Integer> {
+                Cow::Owned(Integer::from(*self))
+            }
+        }
+    )*}
+}
 
-    fn mul(self, other: FieldElement) -> FieldElement {
-        FieldElement::new(self.value * other.value, &self.field)
-    }
+primitive_operand!(u32, u64, i32, i64);
+
+fn add_values(a: &Integer, b: &Integer, field: &Arc<GaloisField>) -> FieldElement {
+    FieldElement::new(Integer::from(a + b), field)
 }
 
-impl Mul<FieldElement> for Integer {
-    type Output = FieldElement;
+fn sub_values(a: &Integer, b: &Integer, field: &Arc<GaloisField>) -> FieldElement {
+    FieldElement::new(Integer::from(a - b), field)
+}
+
+fn mul_values(a: &Integer, b: &Integer, field: &Arc<GaloisField>) -> FieldElement {
+    FieldElement::new(Integer::from(a * b), field)
+}
 
-    fn mul(self, other: FieldElement) -> FieldElement {
-        FieldElement::new(self * other.value, &other.field)
+fn div_values(a: &Integer, b: &Integer, field: &Arc<GaloisField>) -> FieldElement {
+    match b.invert_ref(&field.prime) {
+        Some(inverse) => FieldElement::new(a * Integer::from(inverse), field),
+        None => panic!("division by zero in field of order {}", field.prime)
     }
 }
 
-impl Mul<Integer> for FieldElement {
-    type Output = FieldElement;
+/// Implements an operator between field elements for every combination of
+/// owned and borrowed operands, along with its compound assignment form.
+///
+/// The operators panic when given elements from different fields, use the
+/// checked form of the operation to handle that case instead.
+macro_rules! field_element_op {
+    ($op:ident, $method:ident, $assign_op:ident, $assign_method:ident, $checked:ident) => {
+        impl $op<&FieldElement> for &FieldElement {
+            type Output = FieldElement;
+
+            fn $method(self, other: &FieldElement) -> FieldElement {
+                self.$checked(other).unwrap_or_else(|error| panic!("{}", error))
+            }
+        }
+
+        impl $op<FieldElement> for &FieldElement {
+            type Output = FieldElement;
+
+            fn $method(self, other: FieldElement) -> FieldElement {
+                self.$method(&other)
+            }
+        }
 
-    fn mul(self, other: Integer) -> FieldElement {
-        FieldElement::new(self.value * other, &self.field)
+        impl $op<&FieldElement> for FieldElement {
+            type Output = FieldElement;
+
+            fn $method(self, other: &FieldElement) -> FieldElement {
+                (&self).$method(other)
+            }
+        }
+
+        impl $op for FieldElement {
+            type Output = FieldElement;
+
+            fn $method(self, other: FieldElement) -> FieldElement {
+                (&self).$method(&other)
+            }
+        }
+
+        impl $assign_op<&FieldElement> for FieldElement {
+            fn $assign_method(&mut self, other: &FieldElement) {
+                *self = (&*self).$method(other);
+            }
+        }
+
+        impl $assign_op for FieldElement {
+            fn $assign_method(&mut self, other: FieldElement) {
+                *self = (&*self).$method(&other);
+            }
+        }
     }
 }
 
-impl<'a> Mul<FieldElement> for &'a FieldElement {
-    type Output = FieldElement;
+/// Implements an operator between field elements and integer-like values,
+/// on either side and for every combination of owned and borrowed operands.
+macro_rules! scalar_op {
+    ($op:ident, $method:ident, $assign_op:ident, $assign_method:ident, $compute:ident, $($t:ty),*) => {$(
+        impl $op<&$t> for &FieldElement {
+            type Output = FieldElement;
 
-    fn mul(self, other: FieldElement) -> FieldElement {
-        FieldElement::new(&self.value * other.value, &self.field)
-    }
+            fn $method(self, other: &$t) -> FieldElement {
+                $compute(&self.value, &other.as_integer(), &self.field)
+            }
+        }
+
+        impl $op<$t> for &FieldElement {
+            type Output = FieldElement;
+
+            fn $method(self, other: $t) -> FieldElement {
+                self.$method(&other)
+            }
+        }
+
+        impl $op<&$t> for FieldElement {
+            type Output = FieldElement;
+
+            fn $method(self, other: &$t) -> FieldElement {
+                (&self).$method(other)
+            }
+        }
+
+        impl $op<$t> for FieldElement {
+            type Output = FieldElement;
+
+            fn $method(self, other: $t) -> FieldElement {
+                (&self).$method(&other)
+            }
+        }
+
+        impl $op<&FieldElement> for &$t {
+            type Output = FieldElement;
+
+            fn $method(self, other: &FieldElement) -> FieldElement {
+                $compute(&self.as_integer(), &other.value, &other.field)
+            }
+        }
+
+        impl $op<FieldElement> for &$t {
+            type Output = FieldElement;
+
+            fn $method(self, other: FieldElement) -> FieldElement {
+                self.$method(&other)
+            }
+        }
+
+        impl $op<&FieldElement> for $t {
+            type Output = FieldElement;
+
+            fn $method(self, other: &FieldElement) -> FieldElement {
+                (&self).$method(other)
+            }
+        }
+
+        impl $op<FieldElement> for $t {
+            type Output = FieldElement;
+
+            fn $method(self, other: FieldElement) -> FieldElement {
+                (&self).$method(&other)
+            }
+        }
+
+        impl $assign_op<&$t> for FieldElement {
+            fn $assign_method(&mut self, other: &$t) {
+                *self = (&*self).$method(other);
+            }
+        }
+
+        impl $assign_op<$t> for FieldElement {
+            fn $assign_method(&mut self, other: $t) {
+                *self = (&*self).$method(&other);
+            }
+        }
+    )*}
 }
 
-impl<'a, 'b> Mul<&'b Integer> for &'a FieldElement {
+field_element_op!(Add, add, AddAssign, add_assign, checked_add);
+field_element_op!(Sub, sub, SubAssign, sub_assign, checked_sub);
+field_element_op!(Mul, mul, MulAssign, mul_assign, checked_mul);
+field_element_op!(Div, div, DivAssign, div_assign, checked_div);
+
+scalar_op!(Add, add, AddAssign, add_assign, add_values, Integer, u32, u64, i32, i64);
+scalar_op!(Sub, sub, SubAssign, sub_assign, sub_values, Integer, u32, u64, i32, i64);
+scalar_op!(Mul, mul, MulAssign, mul_assign, mul_values, Integer, u32, u64, i32, i64);
+scalar_op!(Div, div, DivAssign, div_assign, div_values, Integer, u32, u64, i32, i64);
+
+impl Neg for &FieldElement {
     type Output = FieldElement;
 
-    fn mul(self, other: &'b Integer) -> FieldElement {
-        FieldElement::new(
-            Integer::from(&self.value * other),
-            &self.field
-        )
+    fn neg(self) -> FieldElement {
+        FieldElement::new(Integer::from(-&self.value), &self.field)
     }
 }
 
-impl<'a, 'b> Mul<&'b FieldElement> for &'a Integer {
+impl Neg for FieldElement {
     type Output = FieldElement;
 
-    fn mul(self, other: &'b FieldElement) -> FieldElement {
-        FieldElement::new(
-            Integer::from(self * &other.value),
-            &other.field
-        )
+    fn neg(self) -> FieldElement {
+        -&self
     }
 }
 
-impl<'a> Mul<&'a FieldElement> for u32 {
-    type Output = FieldElement;
+/// Sums the elements of an iterator.
+///
+/// Panics on an empty iterator, since there is no field to take zero from.
+impl Sum for FieldElement {
+    fn sum<I: Iterator<Item = FieldElement>>(mut iter: I) -> FieldElement {
+        let first = iter.next().expect("cannot sum an empty iterator of field elements");
+        iter.fold(first, |total, element| total + element)
+    }
+}
 
-    fn mul(self, other: &'a FieldElement) -> FieldElement {
-        FieldElement::new(
-            Integer::from(Integer::from(self) * &other.value),
-            &other.field
-        )
+impl<'a> Sum<&'a FieldElement> for FieldElement {
+    fn sum<I: Iterator<Item = &'a FieldElement>>(iter: I) -> FieldElement {
+        iter.cloned().sum()
     }
 }
 
-impl<'a, 'b> Mul<&'b FieldElement> for &'a FieldElement {
-    type Output = FieldElement;
+/// Multiplies the elements of an iterator.
+///
+/// Panics on an empty iterator, since there is no field to take one from.
+impl Product for FieldElement {
+    fn product<I: Iterator<Item = FieldElement>>(mut iter: I) -> FieldElement {
+        let first = iter.next().expect("cannot multiply an empty iterator of field elements");
+        iter.fold(first, |total, element| total * element)
+    }
+}
 
-    fn mul(self, other: &'b FieldElement) -> FieldElement {
-        FieldElement::new(
-            Integer::from(&self.value * &other.value),
-            &self.field
-        )
+impl<'a> Product<&'a FieldElement> for FieldElement {
+    fn product<I: Iterator<Item = &'a FieldElement>>(iter: I) -> FieldElement {
+        iter.cloned().product()
     }
 }
 
-impl Div for FieldElement {
-    type Output = FieldElement;
+/// Orders elements by value, and elements of different fields by prime.
+impl PartialOrd for FieldElement {
+    fn partial_cmp(&self, other: &FieldElement) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-    fn div(self, other: FieldElement) -> FieldElement {
-        if let Ok(inv) = other.value.clone().invert(&self.field.prime) {
-            FieldElement::new(
-                self.value * &inv,
-                &self.field
-            )
-        } else {
-            unreachable!()
-        }
+impl Ord for FieldElement {
+    fn cmp(&self, other: &FieldElement) -> Ordering {
+        self.value.cmp(&other.value).then_with(|| self.field.prime.cmp(&other.field.prime))
     }
 }
 
-impl Div<Integer> for FieldElement {
-    type Output = FieldElement;
+/// Formats an element as its value followed by the field prime, for
+/// example `12 (mod 223)`.
+impl fmt::Display for FieldElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (mod {})", self.value, self.field.prime)
+    }
+}
 
-    fn div(self, other: Integer) -> FieldElement {
-        if let Ok(inv) = other.invert(&self.field.prime) {
-            FieldElement::new(
-                self.value * &inv,
-                &self.field
-            )
-        } else {
-            unreachable!()
-        }
+/// Splits an element in the format written by `Display` into its unreduced
+/// value and its prime.
+pub(crate) fn parse_display(s: &str) -> Result<(Integer, Integer), String> {
+    let invalid = || format!("invalid field element: {:?}", s);
+    let (value, rest) = s.trim().split_once(" (mod ").ok_or_else(invalid)?;
+    let prime = rest.strip_suffix(')').ok_or_else(invalid)?;
+    let value = value.trim().parse::<Integer>().map_err(|_| invalid())?;
+    let prime = prime.trim().parse::<Integer>().map_err(|_| invalid())?;
+    if prime < 2 {
+        return Err(invalid());
     }
+    Ok((value, prime))
 }
 
-impl<'a, 'b> Div<&'b FieldElement> for &'a FieldElement {
-    type Output = FieldElement;
+/// Parses elements in the format written by `Display`, reducing the value
+/// modulo the prime.
+impl FromStr for FieldElement {
+    type Err = String;
 
-    fn div(self, other: &'b FieldElement) -> FieldElement {
-        if let Ok(inv) = other.value.clone().invert(&self.field.prime) {
-            FieldElement::new(
-                Integer::from(&self.value * &inv),
-                &self.field
-            )
-        } else {
-            unreachable!()
-        }
+    fn from_str(s: &str) -> Result<FieldElement, String> {
+        let (value, prime) = parse_display(s)?;
+        Ok(FieldElement::new(value, &Arc::new(GaloisField::new(prime))))
     }
 }
 
@@ -294,8 +596,8 @@ fn test_galois_fields() {
 
 #[test]
 fn test_fieldelement_add() {
-    let gf_223 = Rc::new(GaloisField::new(Integer::from(223)));
-    let gf_19 = Rc::new(GaloisField::new(Integer::from(19)));
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let gf_19 = Arc::new(GaloisField::new(Integer::from(19)));
 
     let el1 = FieldElement::new(Integer::from(12), &gf_223.clone());
     let el2 = FieldElement::new(Integer::from(222), &gf_223.clone());
@@ -312,7 +614,7 @@ fn test_fieldelement_add() {
 
 #[test]
 fn test_fieldelement_sub() {
-    let gf_223 = Rc::new(GaloisField::new(Integer::from(223)));
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
 
     let el1 = FieldElement::new(Integer::from(123), &gf_223.clone());
     let el2 = FieldElement::new(Integer::from(110), &gf_223.clone());
@@ -322,8 +624,8 @@ fn test_fieldelement_sub() {
 
 #[test]
 fn test_fieldelement_mul() {
-    let gf_223 = Rc::new(GaloisField::new(Integer::from(223)));
-    let gf_19 = Rc::new(GaloisField::new(Integer::from(19)));
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let gf_19 = Arc::new(GaloisField::new(Integer::from(19)));
 
     let el1 = FieldElement::new(Integer::from(123), &gf_223.clone());
     let el2 = FieldElement::new(Integer::from(110), &gf_223.clone());
@@ -337,8 +639,8 @@ fn test_fieldelement_mul() {
 
 #[test]
 fn test_fieldelement_div() {
-    let gf_223 = Rc::new(GaloisField::new(Integer::from(223)));
-    let gf_19 = Rc::new(GaloisField::new(Integer::from(19)));
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let gf_19 = Arc::new(GaloisField::new(Integer::from(19)));
 
     let el1 = FieldElement::new(Integer::from(123), &gf_223.clone());
     let el2 = FieldElement::new(Integer::from(110), &gf_223.clone());
@@ -353,9 +655,126 @@ fn test_fieldelement_div() {
     assert_eq!(el1 / el2, FieldElement::new(Integer::from(9), &gf_19.clone()));
 }
 
+#[test]
+fn test_fieldelement_mixed_operators() {
+    let gf_19 = Arc::new(GaloisField::new(Integer::from(19)));
+    let el = |value: i64| FieldElement::new(Integer::from(value), &gf_19);
+
+    assert_eq!(&el(7) + 15u64, el(3));
+    assert_eq!(15i64 + el(7), el(3));
+    assert_eq!(el(7) - &Integer::from(9), el(17));
+    assert_eq!(&Integer::from(2) - &el(7), el(14));
+    assert_eq!(el(7) * -1i64, el(12));
+    assert_eq!(2u32 * &el(7), el(14));
+    assert_eq!(el(2) / 7u64, el(3));
+    assert_eq!(&Integer::from(2) / el(7), el(3));
+    assert_eq!(-el(7), el(12));
+    assert_eq!(-&el(0), el(0));
+
+    let mut x = el(5);
+    x += el(10);
+    x -= &el(1);
+    x *= 3i32;
+    x /= &Integer::from(2);
+    assert_eq!(x, el(2));
+
+    let elements = [el(3), el(5), el(11)];
+    assert_eq!(elements.iter().sum::<FieldElement>(), el(0));
+    assert_eq!(elements.iter().cloned().product::<FieldElement>(), el(13));
+
+    assert!(el(3) < el(5));
+    assert_eq!(elements.iter().max(), Some(&el(11)));
+}
+
+#[test]
+fn test_fieldelement_mismatched_fields() {
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let gf_19 = Arc::new(GaloisField::new(Integer::from(19)));
+    let other_gf_19 = Arc::new(GaloisField::new(Integer::from(19)));
+
+    let a = FieldElement::new(Integer::from(12), &gf_223);
+    let b = FieldElement::new(Integer::from(7), &gf_19);
+    let c = FieldElement::new(Integer::from(5), &other_gf_19);
+
+    assert!(a.checked_add(&b).is_err());
+    assert!(a.checked_sub(&b).is_err());
+    assert!(a.checked_mul(&b).is_err());
+    assert!(a.checked_div(&b).is_err());
+
+    // Separately created fields with the same prime are the same field
+    assert_eq!(b.checked_add(&c), Ok(FieldElement::new(Integer::from(12), &gf_19)));
+    assert!(b.checked_div(&c.zero()).is_err());
+}
+
+#[test]
+#[should_panic(expected = "different fields")]
+fn test_fieldelement_mismatched_fields_panic() {
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let gf_19 = Arc::new(GaloisField::new(Integer::from(19)));
+
+    let _ = FieldElement::new(Integer::from(12), &gf_223) * FieldElement::new(Integer::from(7), &gf_19);
+}
+
+#[test]
+fn test_fieldelement_batch_invert() {
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let el = |value: u32| FieldElement::new(Integer::from(value), &gf_223);
+
+    let original: Vec<FieldElement> = (1..50).map(|value| el(value * 4)).collect();
+    let mut elements = original.clone();
+    assert_eq!(FieldElement::batch_invert(&mut elements), Ok(()));
+    for (element, inverse) in original.iter().zip(elements.iter()) {
+        assert_eq!(element * inverse, el(1));
+    }
+
+    let mut single = vec![el(110)];
+    assert_eq!(FieldElement::batch_invert(&mut single), Ok(()));
+    assert_eq!(single, vec![el(1) / el(110)]);
+
+    let mut empty: Vec<FieldElement> = Vec::new();
+    assert_eq!(FieldElement::batch_invert(&mut empty), Ok(()));
+
+    let mut with_zero = vec![el(3), el(0), el(5)];
+    assert!(FieldElement::batch_invert(&mut with_zero).is_err());
+    assert_eq!(with_zero, vec![el(3), el(0), el(5)]);
+}
+
+#[test]
+fn test_fieldelement_display_and_parse() {
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let el = FieldElement::new(Integer::from(12), &gf_223);
+
+    assert_eq!(el.to_string(), "12 (mod 223)");
+    assert_eq!(el.to_string().parse::<FieldElement>(), Ok(el));
+    assert_eq!("300 (mod 223)".parse::<FieldElement>().unwrap().value, 77);
+    assert!("12".parse::<FieldElement>().is_err());
+    assert!("12 (mod 1)".parse::<FieldElement>().is_err());
+    assert!("x (mod 223)".parse::<FieldElement>().is_err());
+}
+
+#[test]
+fn test_field_sqrt() {
+    // 223 = 3 (mod 4) and 17 = 1 (mod 4) take different paths through
+    // Tonelli-Shanks.
+    for prime in [223, 17, 97] {
+        let field = Arc::new(GaloisField::new(Integer::from(prime)));
+        for value in 0..prime {
+            let element = FieldElement::new(Integer::from(value), &field);
+            let is_residue = value == 0 || Integer::from(value).legendre(&field.prime) == 1;
+            match element.sqrt() {
+                Some(root) => {
+                    assert!(is_residue);
+                    assert_eq!(&root * &root, element);
+                },
+                None => assert!(!is_residue)
+            }
+        }
+    }
+}
+
 #[test]
 fn test_pow() {
-    let gf_19 = Rc::new(GaloisField::new(Integer::from(19)));
+    let gf_19 = Arc::new(GaloisField::new(Integer::from(19)));
 
     let n = FieldElement::new(Integer::from(7), &gf_19.clone());
     assert_eq!(n.pow(&Integer::from(3)), FieldElement::new(Integer::from(1), &gf_19.clone()));
@@ -363,3 +782,24 @@ fn test_pow() {
     let n = FieldElement::new(Integer::from(9), &gf_19.clone());
     assert_eq!(n.pow(&Integer::from(12)), FieldElement::new(Integer::from(7), &gf_19.clone()));
 }
+
+#[test]
+fn test_montgomery_field() {
+    let gf_223 = Arc::new(GaloisField::new(Integer::from(223)));
+    let mont_223 = Arc::new(GaloisField::new_montgomery(Integer::from(223)));
+    assert_eq!(gf_223, mont_223);
+
+    for (a, b) in [(0i64, 5i64), (12, 222), (123, 110), (300, -7)] {
+        let x = FieldElement::new(Integer::from(a), &gf_223);
+        let y = FieldElement::new(Integer::from(b), &gf_223);
+        let mx = FieldElement::new(Integer::from(a), &mont_223);
+        let my = FieldElement::new(Integer::from(b), &mont_223);
+
+        assert_eq!(&mx * &my, &x * &y);
+        assert_eq!(&mx * b, &x * b);
+        assert_eq!(mx.pow(&Integer::from(b.abs())), x.pow(&Integer::from(b.abs())));
+        if !y.is_zero() {
+            assert_eq!(&mx / &my, &x / &y);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Hex encoding and decoding of bytes and crate types
+//!
+//! Points are written as compressed SEC, signatures as DER, x-only keys as
+//! their 32-byte x coordinate and field elements as their zero-padded value.
+//! Types that need a curve to be parsed are read on secp256k1.
+//!
+//! Signatures and x-only keys also display as hex and parse from it with
+//! `FromStr`. Points and field elements display in a readable form, so their
+//! hex is only read through `FromHex`.
+use rug::Integer;
+use rug::integer::Order;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use programmingbitcoin::ecdsa::*;
+use programmingbitcoin::ellipticcurve::*;
+use programmingbitcoin::finitefield::*;
+use programmingbitcoin::serialization::*;
+
+/// Values that can be written as a hex string
+pub trait ToHex {
+    /// Returns the lowercase hex encoding of the value
+    fn to_hex(&self) -> String;
+}
+
+/// Values that can be read from a hex string
+pub trait FromHex: Sized {
+    /// Parses a value from hex, accepting either case
+    fn from_hex(hex: &str) -> Result<Self, String>;
+}
+
+impl ToHex for [u8] {
+    fn to_hex(&self) -> String {
+        self.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl ToHex for Vec<u8> {
+    fn to_hex(&self) -> String {
+        self.as_slice().to_hex()
+    }
+}
+
+impl FromHex for Vec<u8> {
+    fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+        let invalid = || format!("Invalid hex string {:?}", hex);
+        if !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        (0..hex.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid()))
+            .collect()
+    }
+}
+
+impl ToHex for FieldElement {
+    /// Writes the value zero-padded to the byte width of the field's prime
+    fn to_hex(&self) -> String {
+        to_fixed_width(&self.value, coordinate_width(&self.field)).to_hex()
+    }
+}
+
+impl FieldElement {
+    /// Parse a big-endian hex value as an element of the given field,
+    /// rejecting values that are not below the prime.
+    pub fn from_hex_in(hex: &str, field: &Arc<GaloisField>) -> Result<FieldElement, String> {
+        let value = Integer::from_digits::<u8>(&Vec::from_hex(hex)?, Order::MsfBe);
+        if value >= field.prime {
+            return Err(format!("Value {} is not below the field prime", value));
+        }
+        Ok(FieldElement::new(value, field))
+    }
+}
+
+impl ToHex for Point {
+    fn to_hex(&self) -> String {
+        self.as_compressed_sec().to_hex()
+    }
+}
+
+impl FromHex for Point {
+    /// Parses a compressed or uncompressed SEC point on secp256k1
+    fn from_hex(hex: &str) -> Result<Point, String> {
+        Point::from_sec(&Vec::from_hex(hex)?, &CryptographicCurve::secp256k1()).map_err(|e| e.to_string())
+    }
+}
+
+impl ToHex for Signature {
+    fn to_hex(&self) -> String {
+        self.as_der().to_hex()
+    }
+}
+
+impl FromHex for Signature {
+    /// Parses a DER signature on secp256k1
+    fn from_hex(hex: &str) -> Result<Signature, String> {
+        Signature::from_der(&Vec::from_hex(hex)?, &CryptographicCurve::secp256k1())
+    }
+}
+
+/// Writes the signature as DER hex
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+impl FromStr for Signature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Signature, String> {
+        Signature::from_hex(s.trim())
+    }
+}
+
+impl ToHex for XOnlyPublicKey {
+    fn to_hex(&self) -> String {
+        self.serialize().to_hex()
+    }
+}
+
+impl FromHex for XOnlyPublicKey {
+    /// Parses a 32-byte x-only key on secp256k1
+    fn from_hex(hex: &str) -> Result<XOnlyPublicKey, String> {
+        XOnlyPublicKey::parse(&Vec::from_hex(hex)?, &CryptographicCurve::secp256k1()).map_err(|e| e.to_string())
+    }
+}
+
+impl fmt::Display for XOnlyPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+impl FromStr for XOnlyPublicKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<XOnlyPublicKey, String> {
+        XOnlyPublicKey::from_hex(s.trim())
+    }
+}
+
+#[test]
+fn test_hex_bytes() {
+    let bytes = vec![0x00, 0x01, 0xab, 0xff];
+    assert_eq!(bytes.to_hex(), "0001abff");
+    assert_eq!(bytes[1..].to_hex(), "01abff");
+    assert_eq!(Vec::from_hex("0001ABff"), Ok(bytes));
+    assert_eq!(Vec::from_hex(""), Ok(Vec::new()));
+    assert!(Vec::from_hex("abc").is_err());
+    assert!(Vec::from_hex("zz").is_err());
+    assert!(Vec::from_hex("é1").is_err());
+    assert!(Vec::from_hex("+f").is_err());
+    assert!(Vec::from_hex("+1+2").is_err());
+    assert!(Vec::from_hex("-1").is_err());
+}
+
+#[test]
+fn test_hex_crate_types() {
+    let curve = CryptographicCurve::secp256k1();
+    let private_key = PrivateKey::new(curve.make_element(Integer::from(5000)), &curve);
+
+    let sec = "02ffe558e388852f0120e46af2d1b370f85854a8eb0841811ece0e3e03d282d57c";
+    assert_eq!(private_key.public_key.to_hex(), sec);
+    assert_eq!(Point::from_hex(sec), Ok(private_key.public_key.clone()));
+    assert!(Point::from_hex("02ff").is_err());
+
+    let der = "3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c60221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec";
+    let signature: Signature = der.parse().unwrap();
+    assert_eq!(signature.to_string(), der);
+    assert_eq!(Signature::from_hex(&der.to_uppercase()).unwrap().to_hex(), der);
+
+    let (xonly, _) = XOnlyPublicKey::from_point(&private_key.public_key).unwrap();
+    assert_eq!(xonly.to_string(), &sec[2..]);
+    assert_eq!(sec[2..].parse::<XOnlyPublicKey>(), Ok(xonly));
+
+    let field = Arc::new(GaloisField::new(Integer::from(65537)));
+    let element = FieldElement::new(Integer::from(255), &field);
+    assert_eq!(element.to_hex(), "0000ff");
+    assert_eq!(FieldElement::from_hex_in("0000ff", &field), Ok(element));
+    assert!(FieldElement::from_hex_in("010001", &field).is_err());
+}
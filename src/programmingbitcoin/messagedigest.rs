@@ -2,7 +2,12 @@
 use hmac::{Hmac, Mac};
 use rug::Integer;
 use rug::integer::Order;
+use ripemd160::Ripemd160;
 use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+use programmingbitcoin::hex::*;
 
 /// Shorthand for HMAC_SHA256 algorithm
 type HmacSha256 = Hmac<Sha256>;
@@ -12,20 +17,30 @@ pub fn sha256(data: &[u8]) -> Vec<u8> {
     Sha256::digest(data).to_vec()
 }
 
+/// Implements the BIP340 tagged hash.
+///
+/// TaggedHash(tag, x) = SHA256(SHA256(tag) || SHA256(tag) || x) - which keeps
+/// hashes made for different purposes from colliding.
+pub fn tagged_hash(tag: &str, data: &[u8]) -> TaggedHash {
+    let tag_hash = sha256(tag.as_bytes());
+    let mut message = tag_hash.clone();
+    message.extend_from_slice(&tag_hash);
+    message.extend_from_slice(data);
+    TaggedHash::from_slice(&sha256(&message)).unwrap()
+}
+
 /// Implements the Hash256 algorithm.
 ///
 /// Hash256(x) = SHA256(SHA256(x)) - two rounds of SHA-256 on data.
-pub fn hash256(data: &[u8]) -> Vec<u8> {
-    sha256(sha256(data).as_slice())
+pub fn hash256(data: &[u8]) -> Hash256 {
+    Hash256::from_slice(&sha256(&sha256(data))).unwrap()
 }
 
-/// Hash256 that outputs an integer value.
-pub fn hash256_integer(data: &[u8]) -> Integer {
-    // We need an integer with 256-bits (32-bytes) of capacity to match the
-    // size of the SHA-256 digest result.
-    let mut result = Integer::with_capacity(256);
-    result.assign_digits(hash256(data).as_slice(), Order::Msf);
-    result
+/// Implements the Hash160 algorithm.
+///
+/// Hash160(x) = RIPEMD160(SHA256(x)) - as used for addresses.
+pub fn hash160(data: &[u8]) -> Hash160 {
+    Hash160::from_slice(&Ripemd160::digest(&sha256(data))).unwrap()
 }
 
 /// Computes the HMAC_SHA256(K, M) for the given key and data.
@@ -34,3 +49,235 @@ pub fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
     mac.input(data);
     mac.result().code().to_vec()
 }
+
+/// Defines a fixed-size hash type. Hashes marked as reversed are displayed
+/// and parsed with their bytes in reverse order, as Bitcoin does for
+/// transaction and block hashes.
+macro_rules! hash_newtype {
+    ($(#[$meta:meta])* $name:ident, $length:expr, $reversed:expr) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name([u8; $length]);
+
+        impl $name {
+            /// Wraps bytes in the order the hash function produced them
+            pub fn from_bytes(bytes: [u8; $length]) -> $name {
+                $name(bytes)
+            }
+
+            /// Wraps a slice, which must be exactly the length of the hash
+            pub fn from_slice(bytes: &[u8]) -> Result<$name, String> {
+                let mut result = [0u8; $length];
+                if bytes.len() != result.len() {
+                    return Err(format!("Invalid {} length {}", stringify!($name), bytes.len()));
+                }
+                result.copy_from_slice(bytes);
+                Ok($name(result))
+            }
+
+            /// Returns the bytes in the order the hash function produced them
+            pub fn as_bytes(&self) -> &[u8; $length] {
+                &self.0
+            }
+
+            /// Interprets the bytes as a big-endian integer
+            pub fn to_integer(&self) -> Integer {
+                Integer::from_digits(&self.0, Order::Msf)
+            }
+        }
+
+        impl From<[u8; $length]> for $name {
+            fn from(bytes: [u8; $length]) -> $name {
+                $name(bytes)
+            }
+        }
+
+        impl From<$name> for [u8; $length] {
+            fn from(hash: $name) -> [u8; $length] {
+                hash.0
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl ToHex for $name {
+            fn to_hex(&self) -> String {
+                self.0.to_hex()
+            }
+        }
+
+        impl FromHex for $name {
+            fn from_hex(hex: &str) -> Result<$name, String> {
+                $name::from_slice(&Vec::from_hex(hex)?)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let mut bytes = self.0;
+                if $reversed {
+                    bytes.reverse();
+                }
+                write!(f, "{}", bytes.to_hex())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<$name, String> {
+                let mut hash = $name::from_hex(s.trim())?;
+                if $reversed {
+                    hash.0.reverse();
+                }
+                Ok(hash)
+            }
+        }
+    }
+}
+
+hash_newtype!(
+    /// A 32-byte Hash256 digest, displayed in Bitcoin's reversed byte order
+    Hash256, 32, true
+);
+
+hash_newtype!(
+    /// The Hash256 of a serialized transaction, displayed in reversed byte
+    /// order as block explorers and RPC show it
+    Txid, 32, true
+);
+
+hash_newtype!(
+    /// The Hash256 of a serialized block header, displayed in reversed byte
+    /// order as block explorers and RPC show it
+    BlockHash, 32, true
+);
+
+hash_newtype!(
+    /// A 20-byte Hash160 digest, displayed in the order it was produced
+    Hash160, 20, false
+);
+
+hash_newtype!(
+    /// A 32-byte BIP340 tagged hash, displayed in the order it was produced
+    TaggedHash, 32, false
+);
+
+impl Hash256 {
+    /// Returns the Hash256 digest of the given data
+    pub fn digest(data: &[u8]) -> Hash256 {
+        hash256(data)
+    }
+}
+
+impl Txid {
+    /// Returns the id of a serialized transaction
+    pub fn digest(transaction: &[u8]) -> Txid {
+        Txid::from(hash256(transaction))
+    }
+}
+
+impl BlockHash {
+    /// Returns the hash of a serialized block header
+    pub fn digest(header: &[u8]) -> BlockHash {
+        BlockHash::from(hash256(header))
+    }
+}
+
+impl Hash160 {
+    /// Returns the Hash160 digest of the given data
+    pub fn digest(data: &[u8]) -> Hash160 {
+        hash160(data)
+    }
+}
+
+impl From<Hash256> for Txid {
+    fn from(hash: Hash256) -> Txid {
+        Txid(hash.0)
+    }
+}
+
+impl From<Txid> for Hash256 {
+    fn from(txid: Txid) -> Hash256 {
+        Hash256(txid.0)
+    }
+}
+
+impl From<Hash256> for BlockHash {
+    fn from(hash: Hash256) -> BlockHash {
+        BlockHash(hash.0)
+    }
+}
+
+impl From<BlockHash> for Hash256 {
+    fn from(hash: BlockHash) -> Hash256 {
+        Hash256(hash.0)
+    }
+}
+
+#[test]
+fn test_hash256_display() {
+    // The genesis block header and its hash as shown by block explorers
+    let header = Vec::from_hex(
+        "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c"
+    ).unwrap();
+    let displayed = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+
+    let hash = Hash256::digest(&header);
+    assert_eq!(hash.to_string(), displayed);
+    assert_eq!(displayed.parse::<Hash256>(), Ok(hash));
+    assert_eq!(hash.to_hex(), "6fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000");
+    assert_eq!(Hash256::from_hex(&hash.to_hex()), Ok(hash));
+    assert_eq!(hash, hash256(&header));
+    assert!(Hash256::from_slice(&[0; 31]).is_err());
+}
+
+#[test]
+fn test_hash_newtypes() {
+    use programmingbitcoin::base58::*;
+    use programmingbitcoin::ecdsa::*;
+    use programmingbitcoin::serialization::*;
+    use std::collections::HashSet;
+
+    let header = Vec::from_hex(
+        "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c"
+    ).unwrap();
+
+    // The genesis merkle root is the id of its only transaction
+    let txid = Txid::from_slice(&header[36..68]).unwrap();
+    assert_eq!(txid.to_string(), "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+    assert_eq!(txid.to_string().parse::<Txid>(), Ok(txid));
+    assert_eq!(Hash256::from(txid).to_hex(), txid.to_hex());
+
+    let block_hash = BlockHash::digest(&header);
+    assert_eq!(block_hash, BlockHash::from(hash256(&header)));
+    assert_eq!(block_hash.to_string(), "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f");
+    assert_eq!(block_hash.to_integer(), Integer::from_str_radix(&block_hash.to_hex(), 16).unwrap());
+
+    // Hash160 of an empty input, and of a key against its address
+    assert_eq!(hash160(b"").to_string(), "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb");
+    let curve = CryptographicCurve::secp256k1();
+    let private_key = PrivateKey::new(curve.make_element(Integer::from(0x12345deadbeefu64)), &curve);
+    let hash = hash160(&private_key.public_key.as_compressed_sec());
+    let address = base58check_decode(b"1F1Pn2y6pDb68E5nYJJeba4TLg2U7B6KF1").unwrap();
+    assert_eq!(&address[1..], hash.as_ref());
+    assert_eq!(<[u8; 20]>::from(hash), *hash.as_bytes());
+    assert!(Hash160::from_hex(&txid.to_hex()).is_err());
+
+    // Tagged hashes prefix the data with the tag's SHA-256 twice
+    let tag = sha256(b"TapTweak");
+    let tagged = tagged_hash("TapTweak", b"data");
+    assert_eq!(tagged.as_ref(), sha256(&[&tag[..], &tag[..], b"data"].concat()).as_slice());
+    assert_eq!(tagged.to_string(), tagged.to_hex());
+
+    // Hashes are Copy, ordered and hashable
+    let mut set = HashSet::new();
+    set.insert(txid);
+    set.insert(txid);
+    assert_eq!(set.len(), 1);
+    assert!(Txid::from_bytes([0; 32]) < Txid::from_bytes([1; 32]));
+}
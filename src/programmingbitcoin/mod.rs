@@ -1,6 +1,21 @@
 pub mod base58;
+pub mod base64;
+pub mod batchverification;
+pub mod bech32;
+pub mod der;
+pub mod discretelog;
 pub mod ecdsa;
 pub mod ellipticcurve;
+pub mod extensionfield;
 pub mod finitefield;
+pub mod hex;
+pub mod keyencoding;
 pub mod messagedigest;
+pub mod montgomery;
+pub mod polynomial;
+pub mod secp256k1field;
+pub mod secretsharing;
+pub mod segwit;
+#[cfg(feature = "serde")]
+pub mod serdesupport;
 pub mod serialization;
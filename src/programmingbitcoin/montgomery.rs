@@ -0,0 +1,158 @@
+//! Montgomery-form modular arithmetic
+//!
+//! In Montgomery form a value a modulo p is stored as aR mod p for some power
+//! of two R > p. Products can then be reduced with REDC, which needs only
+//! multiplications, masks and shifts instead of a division by p. Converting
+//! in and out of Montgomery form costs a reduction each way, so the savings
+//! come from long chains of operations such as exponentiation.
+//!
+//! GMP already uses Montgomery reduction internally for `pow_mod`, so with
+//! `rug::Integer` limbs this path is usually slower than plain division. It
+//! is only used to exponentiate elements of fields created with
+//! `GaloisField::new_montgomery`, where the base is converted once and the
+//! whole chain of squarings stays in Montgomery form. Single multiplications
+//! never use Montgomery arithmetic.
+//!
+//! The ignored `bench_montgomery` test compares the two on secp256k1. In a
+//! release build the Montgomery path took around 110µs per `pow` against
+//! 10µs for GMP, while point multiplication, which does not exponentiate,
+//! took about 1.8ms either way.
+use rug::Integer;
+
+/// Precomputed values for Montgomery arithmetic modulo an odd prime.
+#[derive(Clone, Debug)]
+pub struct MontgomeryContext {
+    /// The odd modulus
+    pub prime: Integer,
+    /// Number of bits in R, so that R = 2^bits > prime
+    pub bits: u32,
+    /// R - 1, used to take values modulo R
+    mask: Integer,
+    /// -prime^-1 mod R
+    prime_inverse: Integer,
+    /// R^2 mod prime, used to convert values into Montgomery form
+    r_squared: Integer
+}
+
+impl MontgomeryContext {
+    /// Create a new Montgomery context for the given odd prime.
+    pub fn new(prime: &Integer) -> MontgomeryContext {
+        assert!(prime.is_odd(), "Montgomery form requires an odd modulus");
+        let bits = prime.significant_bits();
+        let r = Integer::from(1) << bits;
+        let mask = Integer::from(&r - 1);
+        let inverse = prime.clone().invert(&r).expect("odd prime is invertible modulo R");
+        let prime_inverse = &r - inverse;
+        let r_squared = Integer::from(&r * &r) % prime;
+
+        MontgomeryContext { prime: prime.clone(), bits, mask, prime_inverse, r_squared }
+    }
+
+    /// Montgomery reduction, returns t * R^-1 mod p for 0 <= t < pR.
+    pub fn redc(&self, t: Integer) -> Integer {
+        let m = (Integer::from(&t & &self.mask) * &self.prime_inverse) & &self.mask;
+        let mut u = (t + m * &self.prime) >> self.bits;
+        if u >= self.prime {
+            u -= &self.prime;
+        }
+        u
+    }
+
+    /// Convert a value in [0, p) into Montgomery form
+    pub fn to_montgomery(&self, value: &Integer) -> Integer {
+        self.redc(Integer::from(value * &self.r_squared))
+    }
+
+    /// Convert a value out of Montgomery form
+    pub fn from_montgomery(&self, value: Integer) -> Integer {
+        self.redc(value)
+    }
+
+    /// Multiply two values that are both in Montgomery form
+    pub fn mul(&self, a: &Integer, b: &Integer) -> Integer {
+        self.redc(Integer::from(a * b))
+    }
+
+    /// Raise a value in Montgomery form to a non-negative power, keeping the
+    /// result in Montgomery form.
+    pub fn pow(&self, base: &Integer, exponent: &Integer) -> Integer {
+        // R mod p is one in Montgomery form
+        let mut result = self.to_montgomery(&Integer::from(1));
+        for bit in (0..exponent.significant_bits()).rev() {
+            result = self.mul(&result, &result);
+            if exponent.get_bit(bit) {
+                result = self.mul(&result, base);
+            }
+        }
+        result
+    }
+}
+
+#[test]
+fn test_montgomery_arithmetic() {
+    let prime = Integer::from(223);
+    let context = MontgomeryContext::new(&prime);
+
+    for a in 0..223 {
+        let a = Integer::from(a);
+        let a_mont = context.to_montgomery(&a);
+        assert_eq!(context.from_montgomery(a_mont.clone()), a);
+
+        for b in &[0, 1, 17, 105, 222] {
+            let b = Integer::from(*b);
+            let b_mont = context.to_montgomery(&b);
+            let expected = Integer::from(&a * &b) % &prime;
+            assert_eq!(context.from_montgomery(context.mul(&a_mont, &b_mont)), expected);
+
+            let expected = a.clone().pow_mod(&b, &prime).unwrap();
+            assert_eq!(context.from_montgomery(context.pow(&a_mont, &b)), expected);
+        }
+    }
+}
+
+/// Compares Montgomery and GMP based exponentiation and point multiplication
+/// on secp256k1.
+///
+/// Run with `cargo test bench_montgomery -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn bench_montgomery() {
+    use programmingbitcoin::ellipticcurve::*;
+    use programmingbitcoin::finitefield::*;
+    use rug::ops::*;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    let gx = Integer::from_str_radix(
+        "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798", 16
+    ).unwrap();
+    let gy = Integer::from_str_radix(
+        "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8", 16
+    ).unwrap();
+    let p = Integer::from(2).pow(256) - Integer::from(2).pow(32) - Integer::from(977);
+    let exponent = Integer::from(&p - 2);
+    let scalar = Integer::from_str_radix(
+        "fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210", 16
+    ).unwrap();
+
+    let fields = vec![
+        ("division", Arc::new(GaloisField::new(p.clone()))),
+        ("montgomery", Arc::new(GaloisField::new_montgomery(p)))
+    ];
+    for (name, field) in fields {
+        let element = FieldElement::new(gx.clone(), &field);
+        let start = Instant::now();
+        for _ in 0..100 {
+            element.pow(&exponent);
+        }
+        println!("{:>10} FieldElement::pow: {:?} per call", name, start.elapsed() / 100);
+
+        let curve = FiniteEllipticCurve::new(EllipticCurve::new(Integer::from(0), Integer::from(7)), &field);
+        let generator = curve.make_point_integral(gx.clone(), gy.clone()).unwrap();
+        let start = Instant::now();
+        for _ in 0..10 {
+            let _ = &generator * &scalar;
+        }
+        println!("{:>10} point multiplication: {:?} per call", name, start.elapsed() / 10);
+    }
+}
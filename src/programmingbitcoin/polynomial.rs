@@ -0,0 +1,390 @@
+//! Polynomials with coefficients in a Galois field
+//!
+//! Polynomials over F_p underpin secret sharing schemes, where a secret is
+//! hidden as the constant term of a random polynomial and recovered from
+//! enough evaluations by Lagrange interpolation.
+use rug::Integer;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::Arc;
+
+use programmingbitcoin::finitefield::*;
+
+/// A polynomial over a Galois field.
+///
+/// Coefficients are stored from lowest to highest degree with no trailing
+/// zeros, so the zero polynomial has no coefficients at all.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    pub coefficients: Vec<FieldElement>,
+    pub field: Arc<GaloisField>
+}
+
+impl Polynomial {
+    /// Create a new polynomial from coefficients, lowest degree first.
+    pub fn new(coefficients: Vec<FieldElement>, field: &Arc<GaloisField>) -> Polynomial {
+        let mut polynomial = Polynomial { coefficients, field: field.clone() };
+        polynomial.trim();
+        polynomial
+    }
+
+    /// Create a new polynomial from integer coefficients, lowest degree first.
+    pub fn from_integers(coefficients: Vec<Integer>, field: &Arc<GaloisField>) -> Polynomial {
+        let coefficients = coefficients.into_iter().map(|c| FieldElement::new(c, field)).collect();
+        Self::new(coefficients, field)
+    }
+
+    /// Returns the zero polynomial
+    pub fn zero(field: &Arc<GaloisField>) -> Polynomial {
+        Polynomial { coefficients: Vec::new(), field: field.clone() }
+    }
+
+    /// Returns the constant polynomial with the given value
+    pub fn constant(value: FieldElement) -> Polynomial {
+        let field = value.field.clone();
+        Self::new(vec![value], &field)
+    }
+
+    /// Returns the polynomial x - root
+    pub fn linear(root: &FieldElement) -> Polynomial {
+        let field = root.field.clone();
+        Self::new(vec![-root, root.one()], &field)
+    }
+
+    /// Indicates whether or not this is the zero polynomial
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    /// Returns the degree of the polynomial, or None for the zero polynomial
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    /// Returns the coefficient of the highest degree term
+    pub fn leading_coefficient(&self) -> Option<&FieldElement> {
+        self.coefficients.last()
+    }
+
+    /// Evaluate the polynomial at a point using Horner's method.
+    pub fn evaluate(&self, x: &FieldElement) -> FieldElement {
+        let zero = FieldElement::new(Integer::from(0), &self.field);
+        self.coefficients.iter().rev().fold(zero, |acc, c| &(&acc * x) + c)
+    }
+
+    /// Returns this polynomial with every coefficient multiplied by a scalar
+    pub fn scale(&self, scalar: &FieldElement) -> Polynomial {
+        let coefficients = self.coefficients.iter().map(|c| c * scalar).collect();
+        Self::new(coefficients, &self.field)
+    }
+
+    /// Returns this polynomial scaled so its leading coefficient is one.
+    pub fn monic(&self) -> Polynomial {
+        match self.leading_coefficient() {
+            Some(lead) => self.scale(&Field::inv(lead).unwrap()),
+            None => self.clone()
+        }
+    }
+
+    /// Divides this polynomial by another, returning the quotient and the
+    /// remainder.
+    pub fn div_rem(&self, divisor: &Polynomial) -> Result<(Polynomial, Polynomial), String> {
+        let divisor_degree = match divisor.degree() {
+            Some(degree) => degree,
+            None => return Err("Cannot divide by the zero polynomial".to_string())
+        };
+        if self.field != divisor.field {
+            return Err("Cannot divide polynomials over different fields".to_string());
+        }
+
+        let lead_inverse = Field::inv(divisor.leading_coefficient().unwrap()).unwrap();
+        let mut remainder = self.clone();
+        let mut quotient = vec![FieldElement::new(Integer::from(0), &self.field); self.coefficients.len()];
+
+        while let Some(degree) = remainder.degree() {
+            if degree < divisor_degree {
+                break;
+            }
+            let shift = degree - divisor_degree;
+            let factor = remainder.leading_coefficient().unwrap() * &lead_inverse;
+            for (i, d) in divisor.coefficients.iter().enumerate() {
+                remainder.coefficients[shift + i] = &remainder.coefficients[shift + i] - &(&factor * d);
+            }
+            quotient[shift] = factor;
+            remainder.trim();
+        }
+
+        Ok((Self::new(quotient, &self.field), remainder))
+    }
+
+    /// Returns the monic greatest common divisor of two polynomials.
+    ///
+    /// The GCD of two zero polynomials is the zero polynomial.
+    pub fn gcd(&self, other: &Polynomial) -> Polynomial {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        while !b.is_zero() {
+            let (_, remainder) = a.div_rem(&b).expect("divisor is not zero");
+            a = b;
+            b = remainder;
+        }
+        a.monic()
+    }
+
+    /// Returns the unique polynomial of lowest degree passing through every
+    /// point, using Lagrange interpolation.
+    ///
+    /// Returns an error if no points are given or if two points share the
+    /// same x coordinate.
+    pub fn interpolate(points: &[(FieldElement, FieldElement)]) -> Result<Polynomial, String> {
+        let field = match points.first() {
+            Some((x, _)) => x.field.clone(),
+            None => return Err("Cannot interpolate without any points".to_string())
+        };
+
+        let mut result = Self::zero(&field);
+        for (i, (xi, yi)) in points.iter().enumerate() {
+            let mut basis = Self::constant(yi.clone());
+            let mut denominator = xi.one();
+            for (j, (xj, _)) in points.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                if xi == xj {
+                    return Err(format!("Duplicate x coordinate {} in interpolation", xi));
+                }
+                basis = &basis * &Self::linear(xj);
+                denominator = &denominator * &(xi - xj);
+            }
+            result = &result + &basis.scale(&Field::inv(&denominator).unwrap());
+        }
+        Ok(result)
+    }
+
+    /// Returns the distinct roots of the polynomial in ascending order.
+    ///
+    /// The roots are found by taking the GCD with x^p - x, which keeps only
+    /// the linear factors, and then splitting that product with the
+    /// Cantor-Zassenhaus method.
+    pub fn roots(&self) -> Result<Vec<FieldElement>, String> {
+        if self.is_zero() {
+            return Err("Every element is a root of the zero polynomial".to_string());
+        }
+
+        let mut roots = Vec::new();
+        if self.field.prime == 2 {
+            for value in 0..2 {
+                let x = FieldElement::new(Integer::from(value), &self.field);
+                if self.evaluate(&x).is_zero() {
+                    roots.push(x);
+                }
+            }
+            return Ok(roots);
+        }
+
+        let monic = self.monic();
+        let x = Self::from_integers(vec![Integer::from(0), Integer::from(1)], &self.field);
+        let x_to_p = x.pow_mod(&self.field.prime, &monic);
+        let linear_factors = monic.gcd(&(&x_to_p - &x));
+
+        linear_factors.split_roots(&mut roots);
+        roots.sort();
+        Ok(roots)
+    }
+
+    /// Collects the roots of a monic polynomial that is a product of distinct
+    /// linear factors.
+    fn split_roots(&self, roots: &mut Vec<FieldElement>) {
+        match self.degree() {
+            None | Some(0) => return,
+            Some(1) => {
+                roots.push(-&self.coefficients[0]);
+                return;
+            },
+            _ => {}
+        }
+
+        // gcd((x + a)^((p - 1) / 2) - 1, f) picks out the roots r for which
+        // r + a is a quadratic residue, which splits f for most choices of a.
+        let exponent = Integer::from(&self.field.prime - 1) >> 1;
+        let one = Self::from_integers(vec![Integer::from(1)], &self.field);
+        let mut offset = Integer::from(0);
+        loop {
+            let shifted = Self::from_integers(vec![offset.clone(), Integer::from(1)], &self.field);
+            let factor = self.gcd(&(&shifted.pow_mod(&exponent, self) - &one));
+            let degree = factor.degree().unwrap_or(0);
+            if degree > 0 && Some(degree) < self.degree() {
+                let (cofactor, _) = self.div_rem(&factor).unwrap();
+                factor.split_roots(roots);
+                cofactor.split_roots(roots);
+                return;
+            }
+            offset += 1;
+        }
+    }
+
+    /// Raises this polynomial to a power modulo another polynomial.
+    fn pow_mod(&self, exponent: &Integer, modulus: &Polynomial) -> Polynomial {
+        let mut result = Self::from_integers(vec![Integer::from(1)], &self.field).div_rem(modulus).unwrap().1;
+        let base = self.div_rem(modulus).unwrap().1;
+        for bit in (0..exponent.significant_bits()).rev() {
+            result = (&result * &result).div_rem(modulus).unwrap().1;
+            if exponent.get_bit(bit) {
+                result = (&result * &base).div_rem(modulus).unwrap().1;
+            }
+        }
+        result
+    }
+
+    /// Removes trailing zero coefficients
+    fn trim(&mut self) {
+        while self.coefficients.last().is_some_and(|c| c.is_zero()) {
+            self.coefficients.pop();
+        }
+    }
+}
+
+impl<'b> Add<&'b Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: &'b Polynomial) -> Polynomial {
+        let (longer, shorter) =
+            if self.coefficients.len() >= other.coefficients.len() {
+                (self, other)
+            } else {
+                (other, self)
+            };
+
+        let mut coefficients = longer.coefficients.clone();
+        for (c, s) in coefficients.iter_mut().zip(shorter.coefficients.iter()) {
+            *c = &*c + s;
+        }
+        Polynomial::new(coefficients, &self.field)
+    }
+}
+
+impl<'b> Sub<&'b Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, other: &'b Polynomial) -> Polynomial {
+        self + &(-other)
+    }
+}
+
+impl<'b> Mul<&'b Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, other: &'b Polynomial) -> Polynomial {
+        if self.is_zero() || other.is_zero() {
+            return Polynomial::zero(&self.field);
+        }
+
+        let zero = FieldElement::new(Integer::from(0), &self.field);
+        let mut coefficients = vec![zero; self.coefficients.len() + other.coefficients.len() - 1];
+        for (i, a) in self.coefficients.iter().enumerate() {
+            for (j, b) in other.coefficients.iter().enumerate() {
+                coefficients[i + j] = &coefficients[i + j] + &(a * b);
+            }
+        }
+        Polynomial::new(coefficients, &self.field)
+    }
+}
+
+impl Neg for &Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        let coefficients = self.coefficients.iter().map(|c| -c).collect();
+        Polynomial::new(coefficients, &self.field)
+    }
+}
+
+impl Neg for Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        -&self
+    }
+}
+
+macro_rules! owned_polynomial_op {
+    ($($op:ident, $method:ident);*) => {$(
+        impl $op for Polynomial {
+            type Output = Polynomial;
+
+            fn $method(self, other: Polynomial) -> Polynomial {
+                (&self).$method(&other)
+            }
+        }
+    )*}
+}
+
+owned_polynomial_op!(Add, add; Sub, sub; Mul, mul);
+
+#[test]
+fn test_polynomial_arithmetic() {
+    let field = Arc::new(GaloisField::new(Integer::from(223)));
+    let poly = |coefficients: &[i32]| {
+        Polynomial::from_integers(coefficients.iter().map(|&c| Integer::from(c)).collect(), &field)
+    };
+
+    // (x + 1)(x - 2) = x^2 - x - 2
+    let a = poly(&[1, 1]);
+    let b = poly(&[-2, 1]);
+    let product = &a * &b;
+    assert_eq!(product, poly(&[-2, -1, 1]));
+    assert_eq!(product.degree(), Some(2));
+    assert_eq!(&product - &product, Polynomial::zero(&field));
+    assert_eq!(Polynomial::zero(&field).degree(), None);
+    assert_eq!(&a + &b, poly(&[-1, 2]));
+
+    let dividend = &(&product * &poly(&[5, 0, 3])) + &poly(&[7, 1]);
+    let (quotient, remainder) = dividend.div_rem(&product).unwrap();
+    assert_eq!(quotient, poly(&[5, 0, 3]));
+    assert_eq!(remainder, poly(&[7, 1]));
+    assert!(dividend.div_rem(&Polynomial::zero(&field)).is_err());
+
+    assert_eq!((&a * &poly(&[3, 4])).gcd(&product.scale(&FieldElement::new(Integer::from(9), &field))), a);
+    assert_eq!(a.gcd(&b), poly(&[1]));
+
+    let x = FieldElement::new(Integer::from(10), &field);
+    assert_eq!(product.evaluate(&x), FieldElement::new(Integer::from(88), &field));
+}
+
+#[test]
+fn test_polynomial_interpolation() {
+    let field = Arc::new(GaloisField::new(Integer::from(223)));
+    let element = |value: i32| FieldElement::new(Integer::from(value), &field);
+    let polynomial = Polynomial::from_integers(
+        vec![Integer::from(42), Integer::from(17), Integer::from(200), Integer::from(3)], &field
+    );
+
+    let points: Vec<_> = (1..5).map(|x| (element(x), polynomial.evaluate(&element(x)))).collect();
+    assert_eq!(Polynomial::interpolate(&points).unwrap(), polynomial);
+
+    // Fewer points give a different, lower degree polynomial
+    let fewer = Polynomial::interpolate(&points[..3]).unwrap();
+    assert_eq!(fewer.degree(), Some(2));
+    assert!(fewer != polynomial);
+
+    assert!(Polynomial::interpolate(&[]).is_err());
+    assert!(Polynomial::interpolate(&[points[0].clone(), points[0].clone()]).is_err());
+}
+
+#[test]
+fn test_polynomial_roots() {
+    let field = Arc::new(GaloisField::new(Integer::from(223)));
+    let element = |value: i32| FieldElement::new(Integer::from(value), &field);
+
+    // (x - 3)(x - 17)^2(x - 200)(x^2 + 1), where x^2 + 1 has no roots since
+    // 223 = 3 (mod 4)
+    let mut polynomial = Polynomial::from_integers(vec![Integer::from(1), Integer::from(0), Integer::from(1)], &field);
+    for root in &[3, 17, 17, 200] {
+        polynomial = &polynomial * &Polynomial::linear(&element(*root));
+    }
+    assert_eq!(polynomial.roots().unwrap(), vec![element(3), element(17), element(200)]);
+    assert!(Polynomial::zero(&field).roots().is_err());
+
+    let binary = Arc::new(GaloisField::new(Integer::from(2)));
+    let polynomial = Polynomial::from_integers(vec![Integer::from(0), Integer::from(1), Integer::from(1)], &binary);
+    assert_eq!(polynomial.roots().unwrap().len(), 2);
+}
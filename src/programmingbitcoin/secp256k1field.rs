@@ -0,0 +1,321 @@
+//! Fixed-width arithmetic in the secp256k1 base field
+//!
+//! `FieldElement` keeps its value in a heap allocated `rug::Integer` and
+//! carries its field around with it. The element defined here instead holds
+//! its value in four 64-bit limbs and is specialized for the secp256k1 prime
+//! p = 2^256 - 2^32 - 977, so it is `Copy` and never allocates.
+//!
+//! Reduction uses the shape of the prime: since 2^256 = 2^32 + 977 (mod p),
+//! the high half of a 512-bit product can be folded back into the low half
+//! by multiplying it by the small constant 2^32 + 977.
+use rug::Integer;
+use rug::integer::Order;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use programmingbitcoin::finitefield::*;
+
+/// The secp256k1 prime as little-endian 64-bit limbs
+const PRIME: [u64; 4] = [
+    0xffff_fffe_ffff_fc2f,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff
+];
+
+/// p - 2, the exponent used to invert by Fermat's little theorem
+const INVERSE_EXPONENT: [u64; 4] = [
+    0xffff_fffe_ffff_fc2d,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff
+];
+
+/// (p + 1) / 4, the exponent used to take square roots since p = 3 (mod 4)
+const SQRT_EXPONENT: [u64; 4] = [
+    0xffff_ffff_bfff_ff0c,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x3fff_ffff_ffff_ffff
+];
+
+/// 2^256 - p = 2^32 + 977
+const REDUCTION_CONSTANT: u64 = 0x1_0000_03d1;
+
+/// An element of the secp256k1 base field stored as fixed-width limbs.
+///
+/// The limbs are little-endian and always hold a value fully reduced modulo
+/// the secp256k1 prime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Secp256k1FieldElement {
+    limbs: [u64; 4]
+}
+
+impl Secp256k1FieldElement {
+    /// The additive identity
+    pub const ZERO: Secp256k1FieldElement = Secp256k1FieldElement { limbs: [0; 4] };
+
+    /// The multiplicative identity
+    pub const ONE: Secp256k1FieldElement = Secp256k1FieldElement { limbs: [1, 0, 0, 0] };
+
+    /// Create a new element from a small integer value
+    pub fn from_u64(value: u64) -> Secp256k1FieldElement {
+        Secp256k1FieldElement { limbs: [value, 0, 0, 0] }
+    }
+
+    /// Create a new element from an arbitrary integer, reducing it modulo p.
+    pub fn from_integer(value: &Integer) -> Secp256k1FieldElement {
+        let reduced = Self::prime().value_of(value.clone());
+        let digits = reduced.to_digits::<u64>(Order::Lsf);
+        let mut limbs = [0u64; 4];
+        limbs[..digits.len()].copy_from_slice(&digits);
+        Secp256k1FieldElement { limbs }
+    }
+
+    /// Returns the value of this element as an integer
+    pub fn to_integer(&self) -> Integer {
+        Integer::from_digits::<u64>(&self.limbs, Order::Lsf)
+    }
+
+    /// Returns the secp256k1 prime as a Galois field
+    pub fn prime() -> GaloisField {
+        GaloisField::new(Integer::from_digits::<u64>(&PRIME, Order::Lsf))
+    }
+
+    /// Returns this element squared
+    pub fn square(&self) -> Secp256k1FieldElement {
+        *self * *self
+    }
+
+    /// Raise this element to a power given as little-endian 64-bit limbs.
+    fn pow_limbs(&self, exponent: &[u64]) -> Secp256k1FieldElement {
+        let mut result = Self::ONE;
+        for limb in exponent.iter().rev() {
+            for bit in (0..64).rev() {
+                result = result.square();
+                if (limb >> bit) & 1 == 1 {
+                    result = result * *self;
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Adds two 256-bit values, returning the sum and whether it overflowed.
+fn add_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut result = [0u64; 4];
+    let mut carry = false;
+    for i in 0..4 {
+        let (sum, c1) = a[i].overflowing_add(b[i]);
+        let (sum, c2) = sum.overflowing_add(carry as u64);
+        result[i] = sum;
+        carry = c1 || c2;
+    }
+    (result, carry)
+}
+
+/// Subtracts two 256-bit values, returning the difference and whether it
+/// borrowed.
+fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut result = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (diff, b1) = a[i].overflowing_sub(b[i]);
+        let (diff, b2) = diff.overflowing_sub(borrow as u64);
+        result[i] = diff;
+        borrow = b1 || b2;
+    }
+    (result, borrow)
+}
+
+/// Subtracts p once if the value is not already below it.
+fn normalize(value: [u64; 4]) -> [u64; 4] {
+    let (reduced, borrow) = sub_limbs(&value, &PRIME);
+    if borrow { value } else { reduced }
+}
+
+/// Multiplies two 256-bit values into a 512-bit product.
+fn mul_wide(a: &[u64; 4], b: &[u64; 4]) -> [u64; 8] {
+    let mut result = [0u64; 8];
+    for i in 0..4 {
+        let mut carry: u128 = 0;
+        for j in 0..4 {
+            let t = (a[i] as u128) * (b[j] as u128) + (result[i + j] as u128) + carry;
+            result[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        result[i + 4] = carry as u64;
+    }
+    result
+}
+
+/// Reduces a 512-bit value modulo p.
+fn reduce_wide(value: &[u64; 8]) -> [u64; 4] {
+    // value = lo + hi * 2^256 = lo + hi * (2^32 + 977) (mod p), which leaves
+    // at most 34 bits above the low 256.
+    let mut folded = [0u64; 4];
+    let mut carry: u128 = 0;
+    for i in 0..4 {
+        let t = (value[i] as u128) + (value[i + 4] as u128) * (REDUCTION_CONSTANT as u128) + carry;
+        folded[i] = t as u64;
+        carry = t >> 64;
+    }
+
+    // Fold the remaining high bits in the same way
+    let mut result = [0u64; 4];
+    let mut carry = carry * (REDUCTION_CONSTANT as u128);
+    for i in 0..4 {
+        let t = (folded[i] as u128) + carry;
+        result[i] = t as u64;
+        carry = t >> 64;
+    }
+
+    // Overflowing 2^256 again leaves only a small value behind, so adding
+    // the reduction constant for the lost 2^256 cannot overflow.
+    if carry != 0 {
+        result = add_limbs(&result, &[REDUCTION_CONSTANT, 0, 0, 0]).0;
+    }
+    normalize(result)
+}
+
+impl Add for Secp256k1FieldElement {
+    type Output = Secp256k1FieldElement;
+
+    fn add(self, other: Secp256k1FieldElement) -> Secp256k1FieldElement {
+        let (sum, carry) = add_limbs(&self.limbs, &other.limbs);
+        let limbs =
+            if carry {
+                // sum + 2^256 - p = sum + (2^32 + 977), which is below p
+                add_limbs(&sum, &[REDUCTION_CONSTANT, 0, 0, 0]).0
+            } else {
+                normalize(sum)
+            };
+        Secp256k1FieldElement { limbs }
+    }
+}
+
+impl Sub for Secp256k1FieldElement {
+    type Output = Secp256k1FieldElement;
+
+    fn sub(self, other: Secp256k1FieldElement) -> Secp256k1FieldElement {
+        let (difference, borrow) = sub_limbs(&self.limbs, &other.limbs);
+        let limbs =
+            if borrow {
+                add_limbs(&difference, &PRIME).0
+            } else {
+                difference
+            };
+        Secp256k1FieldElement { limbs }
+    }
+}
+
+impl Neg for Secp256k1FieldElement {
+    type Output = Secp256k1FieldElement;
+
+    fn neg(self) -> Secp256k1FieldElement {
+        Self::ZERO - self
+    }
+}
+
+impl Mul for Secp256k1FieldElement {
+    type Output = Secp256k1FieldElement;
+
+    fn mul(self, other: Secp256k1FieldElement) -> Secp256k1FieldElement {
+        Secp256k1FieldElement { limbs: reduce_wide(&mul_wide(&self.limbs, &other.limbs)) }
+    }
+}
+
+impl Div for Secp256k1FieldElement {
+    type Output = Secp256k1FieldElement;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: Secp256k1FieldElement) -> Secp256k1FieldElement {
+        self * other.inv().expect("division by zero")
+    }
+}
+
+impl Field for Secp256k1FieldElement {
+    type Context = ();
+
+    fn make_element(_: &(), value: Integer) -> Secp256k1FieldElement {
+        Secp256k1FieldElement::from_integer(&value)
+    }
+
+    fn zero(&self) -> Secp256k1FieldElement {
+        Self::ZERO
+    }
+
+    fn one(&self) -> Secp256k1FieldElement {
+        Self::ONE
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+
+    fn inv(&self) -> Option<Secp256k1FieldElement> {
+        if self.is_zero() {
+            None
+        } else {
+            Some(self.pow_limbs(&INVERSE_EXPONENT))
+        }
+    }
+
+    fn pow(&self, exponent: &Integer) -> Secp256k1FieldElement {
+        self.pow_limbs(&exponent.to_digits::<u64>(Order::Lsf))
+    }
+
+    fn sqrt(&self) -> Option<Secp256k1FieldElement> {
+        let root = self.pow_limbs(&SQRT_EXPONENT);
+        if root.square() == *self { Some(root) } else { None }
+    }
+}
+
+#[test]
+fn test_secp256k1_field_matches_galois_field() {
+    use rug::rand::RandState;
+    use std::sync::Arc;
+
+    let field = Arc::new(Secp256k1FieldElement::prime());
+    let mut rand = RandState::new();
+
+    // Include values near the edges of the field alongside random ones
+    let prime = field.prime.clone();
+    let mut values = vec![
+        Integer::from(0), Integer::from(1), Integer::from(2),
+        Integer::from(&prime - 1), Integer::from(&prime - 2),
+        Integer::from(1) << 255, (Integer::from(1) << 256) - 1
+    ];
+    for _ in 0..200 {
+        values.push(prime.clone().random_below(&mut rand));
+    }
+
+    for pair in values.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        let fa = FieldElement::new(a.clone(), &field);
+        let fb = FieldElement::new(b.clone(), &field);
+        let sa = Secp256k1FieldElement::from_integer(a);
+        let sb = Secp256k1FieldElement::from_integer(b);
+
+        assert_eq!(sa.to_integer(), fa.value);
+        assert_eq!((sa + sb).to_integer(), (&fa + &fb).value);
+        assert_eq!((sa - sb).to_integer(), (&fa - &fb).value);
+        assert_eq!((sa * sb).to_integer(), (&fa * &fb).value);
+        assert_eq!((-sa).to_integer(), (fa.zero() - fa.clone()).value);
+        assert_eq!(Field::pow(&sa, b).to_integer(), fa.pow(b).value);
+
+        match (sa.inv(), Field::inv(&fa)) {
+            (Some(si), Some(fi)) => {
+                assert_eq!(si.to_integer(), fi.value);
+                assert_eq!((sb / sa).to_integer(), (&fb / &fa).value);
+            },
+            (None, None) => assert!(sa.is_zero()),
+            _ => panic!("inverse mismatch for {}", a)
+        }
+
+        match Field::sqrt(&sa) {
+            Some(root) => assert_eq!(root.square(), sa),
+            None => assert_eq!(a.legendre(&prime), -1)
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Shamir secret sharing of private keys
+//!
+//! A secret s is split by choosing a random polynomial f of degree t - 1 over
+//! the curve order field with f(0) = s, and handing out the evaluations
+//! f(1), ..., f(n) as shares. Any t shares determine f, and so the secret, by
+//! Lagrange interpolation, while fewer than t reveal nothing about it.
+use rug::Integer;
+use rug::integer::Order;
+use std::sync::Arc;
+
+use programmingbitcoin::ecdsa::*;
+use programmingbitcoin::finitefield::*;
+use programmingbitcoin::messagedigest::*;
+use programmingbitcoin::polynomial::*;
+use programmingbitcoin::serialization::*;
+
+/// Number of checksum bytes appended to a serialized share
+const CHECKSUM_LENGTH: usize = 4;
+
+/// A single share of a split private key
+#[derive(Clone, Debug, PartialEq)]
+pub struct Share {
+    /// The x coordinate the polynomial was evaluated at, never zero
+    pub index: u8,
+    /// The number of shares needed to recover the secret
+    pub threshold: u8,
+    /// The polynomial evaluated at the index, modulo the curve order
+    pub value: FieldElement
+}
+
+impl Share {
+    /// Serialize the share as its index, threshold and big-endian value,
+    /// followed by the first four bytes of the Hash256 of those bytes.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = vec![self.index, self.threshold];
+        result.extend(to_fixed_width(&self.value.value, coordinate_width(&self.value.field)));
+        let checksum = hash256(&result);
+        result.extend_from_slice(&checksum.as_bytes()[..CHECKSUM_LENGTH]);
+        result
+    }
+
+    /// Parse a serialized share for the given curve, checking its length and
+    /// checksum.
+    pub fn parse(data: &[u8], curve: &Arc<CryptographicCurve>) -> Result<Share, String> {
+        if data.len() != 2 + coordinate_width(&curve.order) + CHECKSUM_LENGTH {
+            return Err(format!("Invalid share length {}", data.len()));
+        }
+
+        let (payload, checksum) = data.split_at(data.len() - CHECKSUM_LENGTH);
+        if hash256(payload).as_bytes()[..CHECKSUM_LENGTH] != *checksum {
+            return Err("Share checksum mismatch".to_string());
+        }
+        if payload[0] == 0 {
+            return Err("Share index must not be zero".to_string());
+        }
+        if payload[1] == 0 {
+            return Err("Share threshold must not be zero".to_string());
+        }
+
+        let value = Integer::from_digits(&payload[2..], Order::Msf);
+        if value >= curve.order.prime {
+            return Err("Share value is not below the curve order".to_string());
+        }
+
+        Ok(Share { index: payload[0], threshold: payload[1], value: curve.make_element(value) })
+    }
+}
+
+/// Split the secret of a private key into `count` shares, any `threshold` of
+/// which recover it.
+///
+/// The random coefficients of the sharing polynomial are derived with
+/// HMAC-SHA256 from the secret and the caller supplied entropy, so the
+/// entropy must be fresh and unpredictable for every split.
+pub fn split_secret(private_key: &PrivateKey,
+                    threshold: u8,
+                    count: u8,
+                    entropy: &[u8]) -> Result<Vec<Share>, String>
+{
+    if threshold == 0 || threshold > count {
+        return Err(format!("Invalid threshold {} for {} shares", threshold, count));
+    }
+
+    let field = private_key.secret.field.clone();
+    let key = private_key.secret.value.to_digits::<u8>(Order::Msf);
+    let mut coefficients = vec![private_key.secret.clone()];
+    for i in 1..threshold {
+        let mut data = entropy.to_vec();
+        data.push(i);
+        let digest = hmac_sha256(&key, &data);
+        coefficients.push(FieldElement::new(Integer::from_digits(&digest, Order::Msf), &field));
+    }
+    let polynomial = Polynomial::new(coefficients, &field);
+
+    Ok((1..=count).map(|index| {
+        let x = FieldElement::new(Integer::from(index), &field);
+        Share { index, threshold, value: polynomial.evaluate(&x) }
+    }).collect())
+}
+
+/// Recover a private key from a set of shares.
+///
+/// Every share must agree on the threshold and have a distinct index. When
+/// more shares than the threshold are given, the extra shares are checked
+/// against the recovered polynomial so that a corrupted share is rejected
+/// rather than silently producing the wrong key.
+pub fn combine_shares(shares: &[Share], curve: &Arc<CryptographicCurve>) -> Result<PrivateKey, String> {
+    let threshold = match shares.first() {
+        Some(share) => share.threshold as usize,
+        None => return Err("No shares given".to_string())
+    };
+    if shares.iter().any(|share| share.threshold as usize != threshold) {
+        return Err("Shares disagree on the threshold".to_string());
+    }
+    if shares.len() < threshold {
+        return Err(format!("Need {} shares but only {} given", threshold, shares.len()));
+    }
+    for (i, share) in shares.iter().enumerate() {
+        if share.index == 0 {
+            return Err("Share index must not be zero".to_string());
+        }
+        if share.value.field != curve.order {
+            return Err(format!("Share {} is not over the curve order", share.index));
+        }
+        if shares[..i].iter().any(|other| other.index == share.index) {
+            return Err(format!("Duplicate share index {}", share.index));
+        }
+    }
+
+    let point = |share: &Share| (curve.make_element(Integer::from(share.index)), share.value.clone());
+    let points: Vec<_> = shares[..threshold].iter().map(point).collect();
+    let polynomial = Polynomial::interpolate(&points)?;
+
+    for share in &shares[threshold..] {
+        let (x, y) = point(share);
+        if polynomial.evaluate(&x) != y {
+            return Err(format!("Share {} is inconsistent with the others", share.index));
+        }
+    }
+
+    let secret = polynomial.evaluate(&curve.make_element(Integer::from(0)));
+    if secret.is_zero() {
+        return Err("Recovered secret is zero".to_string());
+    }
+    Ok(PrivateKey::new(secret, curve))
+}
+
+#[test]
+fn test_secret_sharing() {
+    let curve = CryptographicCurve::secp256k1();
+    let secret = curve.make_element(hash256(b"custody secret").to_integer());
+    let private_key = PrivateKey::new(secret, &curve);
+
+    let shares = split_secret(&private_key, 3, 5, b"fresh entropy").unwrap();
+    assert_eq!(shares.len(), 5);
+
+    // Any three shares recover the key
+    for subset in &[[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+        let chosen: Vec<Share> = subset.iter().map(|&i| shares[i].clone()).collect();
+        let recovered = combine_shares(&chosen, &curve).unwrap();
+        assert_eq!(recovered.secret, private_key.secret);
+        assert_eq!(recovered.public_key, private_key.public_key);
+    }
+    assert_eq!(combine_shares(&shares, &curve).unwrap().secret, private_key.secret);
+
+    // Different entropy gives different shares of the same secret
+    let other = split_secret(&private_key, 3, 5, b"other entropy").unwrap();
+    assert!(other[0] != shares[0]);
+    assert_eq!(combine_shares(&other[2..], &curve).unwrap().secret, private_key.secret);
+
+    assert!(split_secret(&private_key, 0, 5, b"").is_err());
+    assert!(split_secret(&private_key, 6, 5, b"").is_err());
+}
+
+#[test]
+fn test_share_validation() {
+    let curve = CryptographicCurve::secp256k1();
+    let private_key = PrivateKey::new(curve.make_element(Integer::from(12345)), &curve);
+    let shares = split_secret(&private_key, 2, 4, b"entropy").unwrap();
+
+    for share in &shares {
+        let serialized = share.serialize();
+        assert_eq!(serialized.len(), 38);
+        assert_eq!(Share::parse(&serialized, &curve).unwrap(), *share);
+
+        let mut corrupted = serialized.clone();
+        corrupted[10] ^= 1;
+        assert!(Share::parse(&corrupted, &curve).is_err());
+        assert!(Share::parse(&serialized[1..], &curve).is_err());
+    }
+
+    // Too few, duplicated, mismatched and tampered share sets are rejected
+    assert!(combine_shares(&[], &curve).is_err());
+    assert!(combine_shares(&shares[..1], &curve).is_err());
+    assert!(combine_shares(&[shares[0].clone(), shares[0].clone()], &curve).is_err());
+
+    let mut mismatched = shares.clone();
+    mismatched[1].threshold = 3;
+    assert!(combine_shares(&mismatched, &curve).is_err());
+
+    let mut tampered = shares.clone();
+    tampered[3].value = &tampered[3].value + &curve.make_element(Integer::from(1));
+    assert!(combine_shares(&tampered, &curve).is_err());
+    assert_eq!(combine_shares(&tampered[..3], &curve).unwrap().secret, private_key.secret);
+}
@@ -0,0 +1,262 @@
+//! Native segwit addresses
+//!
+//! A segwit address is the Bech32 encoding of a witness version and a
+//! witness program: the Hash160 of a compressed public key for P2WPKH, the
+//! SHA-256 of a script for P2WSH, and a tweaked x-only public key for P2TR.
+//! Version 0 addresses use Bech32 and later versions use Bech32m.
+use std::fmt;
+use std::str::FromStr;
+
+use programmingbitcoin::bech32::*;
+use programmingbitcoin::ecdsa::*;
+use programmingbitcoin::ellipticcurve::*;
+use programmingbitcoin::messagedigest::*;
+use programmingbitcoin::serialization::*;
+
+/// Highest witness version an address can hold
+const MAX_WITNESS_VERSION: u8 = 16;
+
+/// Shortest and longest witness programs an address can hold
+const MIN_PROGRAM_LENGTH: usize = 2;
+const MAX_PROGRAM_LENGTH: usize = 40;
+
+/// A native segwit address.
+///
+/// The version and program are checked when the address is created, so
+/// every address can be encoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SegwitAddress {
+    network: Network,
+    version: u8,
+    program: Vec<u8>
+}
+
+/// Returns the human-readable part of addresses on the given network
+fn address_hrp(network: Network) -> &'static str {
+    match network {
+        Network::Mainnet => "bc",
+        Network::Testnet | Network::Signet => "tb",
+        Network::Regtest => "bcrt"
+    }
+}
+
+impl SegwitAddress {
+    /// Create an address from a witness version and program, checking their
+    /// lengths against BIP141 and BIP341.
+    pub fn new(network: Network, version: u8, program: Vec<u8>) -> Result<SegwitAddress, String> {
+        if version > MAX_WITNESS_VERSION {
+            return Err(format!("Invalid witness version {}", version));
+        }
+        if program.len() < MIN_PROGRAM_LENGTH || program.len() > MAX_PROGRAM_LENGTH {
+            return Err(format!("Invalid witness program length {}", program.len()));
+        }
+        if version == 0 && program.len() != 20 && program.len() != 32 {
+            return Err(format!("Invalid version 0 witness program length {}", program.len()));
+        }
+        Ok(SegwitAddress { network, version, program })
+    }
+
+    /// Returns the network the address is meant for. Signet shares the
+    /// testnet prefix, so parsed signet addresses are reported as testnet.
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Returns the witness version, from 0 to 16
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the witness program, 2 to 40 bytes long
+    pub fn program(&self) -> &[u8] {
+        &self.program
+    }
+
+    /// Pay to witness public key hash, for the compressed form of the key
+    pub fn p2wpkh(public_key: &Point, network: Network) -> SegwitAddress {
+        let hash = hash160(&public_key.as_compressed_sec());
+        SegwitAddress { network, version: 0, program: hash.as_ref().to_vec() }
+    }
+
+    /// Pay to witness script hash, for the serialized witness script
+    pub fn p2wsh(script: &[u8], network: Network) -> SegwitAddress {
+        SegwitAddress { network, version: 0, program: sha256(script) }
+    }
+
+    /// Pay to taproot, for an internal key and the merkle root of an optional
+    /// script tree. The program is the output key from `taproot_output_key`.
+    pub fn p2tr(internal_key: &XOnlyPublicKey,
+                merkle_root: Option<&[u8]>,
+                network: Network) -> Result<SegwitAddress, String>
+    {
+        let (output_key, _) = taproot_output_key(internal_key, merkle_root)?;
+        Ok(SegwitAddress { network, version: 1, program: output_key.serialize() })
+    }
+
+    /// Parse a segwit address, accepting all lowercase or all uppercase.
+    ///
+    /// Testnet and signet share the "tb" prefix, so those addresses are
+    /// returned as `Testnet`.
+    pub fn parse(address: &str) -> Result<SegwitAddress, String> {
+        let (hrp, data, variant) = bech32_decode(address)?;
+        let network = match hrp.as_str() {
+            "bc" => Network::Mainnet,
+            "tb" => Network::Testnet,
+            "bcrt" => Network::Regtest,
+            _ => return Err(format!("Unknown address prefix {}", hrp))
+        };
+
+        let (&version, program) = data.split_first()
+            .ok_or_else(|| "Address has no witness version".to_string())?;
+        let expected = if version == 0 { Bech32Variant::Bech32 } else { Bech32Variant::Bech32m };
+        if variant != expected {
+            return Err(format!("Version {} addresses must use {:?}", version, expected));
+        }
+        SegwitAddress::new(network, version, convert_bits(program, 5, 8, false)?)
+    }
+
+    /// Returns the output script paying to the address: the version opcode
+    /// followed by a push of the program.
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        // OP_0 is 0x00 while OP_1 through OP_16 are 0x51 through 0x60
+        let version_opcode = if self.version == 0 { 0x00 } else { 0x50 + self.version };
+        let mut result = vec![version_opcode, self.program.len() as u8];
+        result.extend_from_slice(&self.program);
+        result
+    }
+}
+
+impl fmt::Display for SegwitAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let variant = if self.version == 0 { Bech32Variant::Bech32 } else { Bech32Variant::Bech32m };
+        let mut data = vec![self.version];
+        data.extend(convert_bits(&self.program, 8, 5, true).map_err(|_| fmt::Error)?);
+        let address = bech32_encode(address_hrp(self.network), &data, variant).map_err(|_| fmt::Error)?;
+        write!(f, "{}", address)
+    }
+}
+
+impl FromStr for SegwitAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SegwitAddress, String> {
+        SegwitAddress::parse(s.trim())
+    }
+}
+
+/// Tweak a taproot internal key as described in BIP341.
+///
+/// The output key is Q = P + tG, where P is the internal key with an even y
+/// coordinate and t is the "TapTweak" tagged hash of P's x coordinate and
+/// the merkle root, if any. Returns the output key along with the parity of
+/// Q's y coordinate, which script path spends need.
+pub fn taproot_output_key(internal_key: &XOnlyPublicKey,
+                          merkle_root: Option<&[u8]>) -> Result<(XOnlyPublicKey, Parity), String>
+{
+    let curve = CryptographicCurve::secp256k1();
+    let mut data = internal_key.serialize();
+    if let Some(root) = merkle_root {
+        if root.len() != 32 {
+            return Err(format!("Invalid merkle root length {}", root.len()));
+        }
+        data.extend_from_slice(root);
+    }
+
+    let tweak = tagged_hash("TapTweak", &data).to_integer();
+    if tweak >= curve.order.prime {
+        return Err("Taproot tweak is not below the curve order".to_string());
+    }
+    let output = &internal_key.to_point() + &(&curve.make_element(tweak) * &curve.generator_point);
+    XOnlyPublicKey::from_point(&output).ok_or_else(|| "Taproot output key is infinity".to_string())
+}
+
+#[test]
+fn test_segwit_address_vectors() {
+    use programmingbitcoin::hex::*;
+
+    // Test vectors from BIP350, with the scriptPubKey each address pays to
+    let valid = [
+        ("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4", "0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+        ("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7", "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"),
+        ("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y", "5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6"),
+        ("BC1SW50QGDZ25J", "6002751e"),
+        ("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs", "5210751e76e8199196d454941c45d1b3a323"),
+        ("tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy", "0020000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433"),
+        ("tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c", "5120000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433"),
+        ("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0", "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+    ];
+    for &(string, script) in valid.iter() {
+        let address: SegwitAddress = string.parse().unwrap();
+        assert_eq!(address.script_pubkey().to_hex(), script);
+        assert_eq!(address.to_string(), string.to_lowercase());
+    }
+
+    let invalid = [
+        "tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut",
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+        "tb1z0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqglt7rf",
+        "BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+        "tb1q0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq24jc47",
+        "bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4",
+        "BC130XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ7ZWS8R",
+        "bc1pw5dgrnzv",
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav",
+        "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+        "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq",
+        "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v07qwwzcrf",
+        "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vpggkg4j",
+        "bc1gmk9yu"
+    ];
+    for string in invalid.iter() {
+        assert!(SegwitAddress::parse(string).is_err(), "accepted {:?}", string);
+    }
+}
+
+#[test]
+fn test_segwit_address_generation() {
+    use programmingbitcoin::hex::*;
+
+    let curve = CryptographicCurve::secp256k1();
+    let generator = curve.generator_point.clone();
+
+    // The BIP173 examples pay to the generator's key and a script checking it
+    let address = SegwitAddress::p2wpkh(&generator, Network::Mainnet);
+    assert_eq!(address.to_string(), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+    assert_eq!((address.network(), address.version()), (Network::Mainnet, 0));
+    assert_eq!(address.program().to_hex(), "751e76e8199196d454941c45d1b3a323f1433bd6");
+    let mut script = vec![0x21];
+    script.extend(generator.as_compressed_sec());
+    script.push(0xac);
+    let address = SegwitAddress::p2wsh(&script, Network::Testnet);
+    assert_eq!(address.to_string(), "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7");
+
+    // Signet addresses round-trip through their encoding, but parse as testnet
+    let signet = SegwitAddress::p2wsh(&script, Network::Signet);
+    assert_eq!(signet.network(), Network::Signet);
+    assert_eq!(signet.to_string(), address.to_string());
+    let parsed: SegwitAddress = signet.to_string().parse().unwrap();
+    assert_eq!(parsed.network(), Network::Testnet);
+    assert_eq!(parsed.program(), signet.program());
+    assert_eq!(parsed.to_string(), signet.to_string());
+
+    // The first BIP86 receive address, a key path only taproot output
+    let internal_key = XOnlyPublicKey::from_hex("cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115").unwrap();
+    let (output_key, _) = taproot_output_key(&internal_key, None).unwrap();
+    assert_eq!(output_key.to_hex(), "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c");
+    let address = SegwitAddress::p2tr(&internal_key, None, Network::Mainnet).unwrap();
+    assert_eq!(address.to_string(), "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr");
+
+    // A script tree commits to a different output key
+    let tree = SegwitAddress::p2tr(&internal_key, Some(&[0x11; 32]), Network::Mainnet).unwrap();
+    assert!(tree.program() != address.program());
+    assert!(SegwitAddress::p2tr(&internal_key, Some(&[0x11; 31]), Network::Mainnet).is_err());
+
+    let regtest = SegwitAddress::p2tr(&internal_key, None, Network::Regtest).unwrap();
+    assert!(regtest.to_string().starts_with("bcrt1p"));
+    assert_eq!(regtest.to_string().parse(), Ok(regtest));
+
+    assert!(SegwitAddress::new(Network::Mainnet, 0, vec![0; 21]).is_err());
+    assert!(SegwitAddress::new(Network::Mainnet, 17, vec![0; 20]).is_err());
+    assert!(SegwitAddress::new(Network::Mainnet, 2, vec![0; 41]).is_err());
+}
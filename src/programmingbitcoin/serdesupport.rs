@@ -0,0 +1,221 @@
+//! Serde support for field elements, points, signatures and private keys
+//!
+//! Enabled with the `serde` cargo feature. Human-readable formats such as
+//! JSON get strings: points as compressed SEC hex, signatures as DER hex and
+//! field elements in their `Display` form. Binary formats such as CBOR get
+//! the raw bytes instead.
+//!
+//! Points and signatures are deserialized on secp256k1. Private keys are not
+//! serializable by default, so a secret never ends up in a config file by
+//! accident; fields that should hold one opt in with
+//! `#[serde(with = "programmingbitcoin::serdesupport::wif")]`.
+use rug::Integer;
+use rug::integer::Order;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeTuple, Serializer};
+use std::fmt;
+use std::sync::Arc;
+
+use programmingbitcoin::ecdsa::*;
+use programmingbitcoin::ellipticcurve::*;
+use programmingbitcoin::finitefield::*;
+use programmingbitcoin::hex::*;
+use programmingbitcoin::serialization::*;
+
+/// A byte string that deserializes from either bytes or a sequence of bytes
+struct Bytes(Vec<u8>);
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Bytes;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a byte string")
+            }
+
+            fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Bytes, E> {
+                Ok(Bytes(value.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Bytes, E> {
+                Ok(Bytes(value))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Bytes, A::Error> {
+                let mut bytes = Vec::new();
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(Bytes(bytes))
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+/// Serializes an encoding as hex for human-readable formats and as raw bytes
+/// otherwise.
+fn serialize_encoding<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&bytes.to_hex())
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+/// Deserializes an encoding written by `serialize_encoding`
+fn deserialize_encoding<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    if deserializer.is_human_readable() {
+        let hex = String::deserialize(deserializer)?;
+        Vec::from_hex(&hex).map_err(de::Error::custom)
+    } else {
+        Ok(Bytes::deserialize(deserializer)?.0)
+    }
+}
+
+impl Serialize for FieldElement {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            let mut tuple = serializer.serialize_tuple(2)?;
+            tuple.serialize_element(&Bytes(self.value.to_digits::<u8>(Order::MsfBe)))?;
+            tuple.serialize_element(&Bytes(self.field.prime.to_digits::<u8>(Order::MsfBe)))?;
+            tuple.end()
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldElement {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<FieldElement, D::Error> {
+        let (value, prime) = if deserializer.is_human_readable() {
+            let text = String::deserialize(deserializer)?;
+            parse_display(&text).map_err(de::Error::custom)?
+        } else {
+            let (value, prime) = <(Bytes, Bytes)>::deserialize(deserializer)?;
+            (Integer::from_digits::<u8>(&value.0, Order::MsfBe), Integer::from_digits::<u8>(&prime.0, Order::MsfBe))
+        };
+        if prime < 2 || value < 0 || value >= prime {
+            return Err(de::Error::custom("field element value is out of range"));
+        }
+        Ok(FieldElement::new(value, &Arc::new(GaloisField::new(prime))))
+    }
+}
+
+impl Serialize for Point {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_infinity() {
+            return Err(ser::Error::custom("cannot serialize the point at infinity"));
+        }
+        serialize_encoding(&self.as_compressed_sec(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Point {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Point, D::Error> {
+        let sec = deserialize_encoding(deserializer)?;
+        Point::from_sec(&sec, &CryptographicCurve::secp256k1()).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Signature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_encoding(&self.as_der(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Signature, D::Error> {
+        let der = deserialize_encoding(deserializer)?;
+        Signature::from_der(&der, &CryptographicCurve::secp256k1()).map_err(de::Error::custom)
+    }
+}
+
+/// Serializes a `PrivateKey` field as a mainnet WIF string for compressed
+/// public keys. Any WIF key for secp256k1 is accepted when deserializing.
+pub mod wif {
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::Serializer;
+
+    use programmingbitcoin::ecdsa::*;
+    use programmingbitcoin::serialization::*;
+
+    pub fn serialize<S: Serializer>(key: &PrivateKey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&key.as_wif(true, Network::Mainnet))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PrivateKey, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let (key, _, _) = PrivateKey::from_wif(&text, &CryptographicCurve::secp256k1())
+            .map_err(de::Error::custom)?;
+        Ok(key)
+    }
+}
+
+#[test]
+fn test_serde_json() {
+    let curve = CryptographicCurve::secp256k1();
+    let private_key = PrivateKey::new(curve.make_element(Integer::from(5000)), &curve);
+    let public_key = private_key.public_key.clone();
+    let signature = private_key.sign(&Integer::from(1234), &Integer::from(5678));
+
+    let json = serde_json::to_string(&public_key).unwrap();
+    assert_eq!(json, format!("\"{}\"", public_key.as_compressed_sec().to_hex()));
+    assert_eq!(serde_json::from_str::<Point>(&json).unwrap(), public_key);
+
+    let json = serde_json::to_string(&signature).unwrap();
+    assert_eq!(json, format!("\"{}\"", signature.as_der().to_hex()));
+    let decoded: Signature = serde_json::from_str(&json).unwrap();
+    assert_eq!((decoded.r, decoded.s), (signature.r, signature.s));
+
+    let element = FieldElement::new(Integer::from(12), &Arc::new(GaloisField::new(Integer::from(223))));
+    let json = serde_json::to_string(&element).unwrap();
+    assert_eq!(json, "\"12 (mod 223)\"");
+    assert_eq!(serde_json::from_str::<FieldElement>(&json).unwrap(), element);
+    assert!(serde_json::from_str::<FieldElement>("\"300 (mod 223)\"").is_err());
+    assert!(serde_json::from_str::<FieldElement>("\"-1 (mod 223)\"").is_err());
+
+    let mut json = Vec::new();
+    wif::serialize(&private_key, &mut serde_json::Serializer::new(&mut json)).unwrap();
+    let wif_string = String::from_utf8(json).unwrap();
+    assert_eq!(wif_string, format!("\"{}\"", private_key.as_wif(true, Network::Mainnet)));
+    let decoded = wif::deserialize(&mut serde_json::Deserializer::from_str(&wif_string)).unwrap();
+    assert_eq!(decoded.secret, private_key.secret);
+
+    assert!(serde_json::from_str::<Point>("\"02ff\"").is_err());
+    assert!(serde_json::from_str::<Point>("\"zz\"").is_err());
+    assert!(serde_json::to_string(&Point::infinity(&curve.finite_curve)).is_err());
+}
+
+#[test]
+fn test_serde_cbor() {
+    let curve = CryptographicCurve::secp256k1();
+    let private_key = PrivateKey::new(curve.make_element(Integer::from(2018)), &curve);
+    let public_key = private_key.public_key.clone();
+    let signature = private_key.sign(&Integer::from(99), &Integer::from(42));
+
+    // Binary formats carry raw bytes rather than hex
+    let cbor = serde_cbor::to_vec(&public_key).unwrap();
+    assert_eq!(cbor.len(), 35);
+    assert!(cbor.ends_with(&public_key.as_compressed_sec()));
+    assert_eq!(serde_cbor::from_slice::<Point>(&cbor).unwrap(), public_key);
+
+    let cbor = serde_cbor::to_vec(&signature).unwrap();
+    assert!(cbor.ends_with(&signature.as_der()));
+    let decoded: Signature = serde_cbor::from_slice(&cbor).unwrap();
+    assert_eq!((decoded.r, decoded.s), (signature.r, signature.s));
+
+    let element = FieldElement::new(Integer::from(1000), &Arc::new(GaloisField::new(Integer::from(65537))));
+    let cbor = serde_cbor::to_vec(&element).unwrap();
+    assert_eq!(serde_cbor::from_slice::<FieldElement>(&cbor).unwrap(), element);
+}
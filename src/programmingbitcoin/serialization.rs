@@ -1,12 +1,21 @@
 //! Contains traits for serializing values
-use std::rc::Rc;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
 use rug::Integer;
-use rug::ops::*;
 use rug::integer::Order;
 
+use programmingbitcoin::base58::*;
+use programmingbitcoin::der::*;
 use programmingbitcoin::ellipticcurve::*;
 use programmingbitcoin::ecdsa::*;
+use programmingbitcoin::finitefield::*;
 
+/// SEC encoding of points.
+///
+/// As in SEC1, the point at infinity is encoded as the single byte 0x00 in
+/// both formats. It is never a valid public key, so `Point::from_sec` rejects
+/// that encoding.
 pub trait PublicKeySerialization {
     /// Converts a public key value into SEC format.
     fn as_sec(&self) -> Vec<u8>;
@@ -19,157 +28,515 @@ pub trait SignatureSerialization {
     fn as_der(&self) -> Vec<u8>;
 }
 
+pub trait PrivateKeySerialization {
+    /// Converts a private key into Wallet Import Format.
+    fn as_wif(&self, compressed: bool, network: Network) -> String;
+}
+
+/// The Bitcoin network an encoded key or address is meant for.
+///
+/// Signet keys and addresses use the testnet prefixes, so they are encoded
+/// like testnet ones and parse back as `Testnet`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest
+}
+
+/// Returns the number of bytes used to encode each coordinate of a point on
+/// a curve over the given field, 32 for secp256k1.
+pub(crate) fn coordinate_width(field: &GaloisField) -> usize {
+    (field.prime.significant_bits() as usize).div_ceil(8)
+}
+
+/// Encodes a value as big-endian bytes, zero-padded on the left to the given
+/// width.
+pub(crate) fn to_fixed_width(value: &Integer, width: usize) -> Vec<u8> {
+    let digits = value.to_digits::<u8>(Order::MsfBe);
+    let mut result = vec![0u8; width - digits.len()];
+    result.extend(digits);
+    result
+}
+
 impl PublicKeySerialization for Point {
     // SEC encode and given point
     fn as_sec(&self) -> Vec<u8> {
-        let mut result : Vec<u8> = Vec::new();
-        let x_val = self.x.clone().unwrap().value;
-        let y_val = self.y.clone().unwrap().value;
+        let (x, y) = match (self.x.as_ref(), self.y.as_ref()) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return vec![0x00]
+        };
+        let width = coordinate_width(&self.curve.field);
 
-        result.push(0x04);
-        result.append(&mut x_val.to_digits::<u8>(Order::MsfBe));
-        result.append(&mut y_val.to_digits::<u8>(Order::MsfBe));
+        let mut result : Vec<u8> = vec![0x04];
+        result.extend(to_fixed_width(&x.value, width));
+        result.extend(to_fixed_width(&y.value, width));
         result
     }
 
     // Compressed SEC encode a given point
     fn as_compressed_sec(&self) -> Vec<u8> {
-        let mut result : Vec<u8> = Vec::new();
-        let x_val = self.x.clone().unwrap().value;
+        let (x, y) = match (self.x.as_ref(), self.y.as_ref()) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return vec![0x00]
+        };
+        let width = coordinate_width(&self.curve.field);
         let prefix_byte =
-            if self.y.clone().unwrap().value.is_even() {
+            if y.value.is_even() {
                 0x02
             } else {
                 0x03
             };
 
-        result.push(prefix_byte);
-        result.append(&mut x_val.to_digits::<u8>(Order::MsfBe));
+        let mut result : Vec<u8> = vec![prefix_byte];
+        result.extend(to_fixed_width(&x.value, width));
         result
     }
 }
 
+/// Reasons a SEC encoded public key can be rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SecError {
+    /// The input was empty
+    Empty,
+    /// The first byte is not a SEC prefix
+    InvalidPrefix(u8),
+    /// The input is a hybrid encoding, which was not allowed
+    HybridNotAllowed,
+    /// The input length does not match the length its prefix requires
+    InvalidLength { expected: usize, actual: usize },
+    /// A coordinate is not below the field prime
+    CoordinateOutOfRange,
+    /// The coordinates do not satisfy the curve equation, or a compressed x
+    /// coordinate has no matching y
+    NotOnCurve,
+    /// The parity given by a hybrid prefix does not match the y coordinate
+    HybridParityMismatch
+}
+
+impl fmt::Display for SecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SecError::Empty => write!(f, "SEC data is empty"),
+            SecError::InvalidPrefix(prefix) => write!(f, "Invalid SEC prefix 0x{:02x}", prefix),
+            SecError::HybridNotAllowed => write!(f, "Hybrid SEC encoding is not allowed"),
+            SecError::InvalidLength { expected, actual } => {
+                write!(f, "Invalid SEC length {}, expected {}", actual, expected)
+            },
+            SecError::CoordinateOutOfRange => write!(f, "SEC coordinate is not below the field prime"),
+            SecError::NotOnCurve => write!(f, "SEC point is not on the curve"),
+            SecError::HybridParityMismatch => write!(f, "Hybrid SEC prefix does not match y parity")
+        }
+    }
+}
+
+impl Error for SecError {}
+
 impl Point {
-    /// Load SEC formatted public key
-    fn from_sec(data: &Vec<u8>, curve: &Rc<CryptographicCurve>) -> Point {
-        // Uncompressed
-        if data[0] == 0x4 {
-            let x = Integer::from_digits::<u8>(&data[1..33], Order::MsfBe);
-            let y = Integer::from_digits::<u8>(&data[33..65], Order::MsfBe);
-            return curve.make_point_integral(x, y).unwrap();
+    /// Load a SEC formatted public key.
+    ///
+    /// Accepts the compressed (0x02, 0x03) and uncompressed (0x04) encodings
+    /// with exactly the right length, and only if the encoded point is on
+    /// the curve.
+    pub fn from_sec(data: &[u8], curve: &Arc<CryptographicCurve>) -> Result<Point, SecError> {
+        Self::parse_sec(data, curve, false)
+    }
+
+    /// Load a SEC formatted public key, also accepting the hybrid encodings
+    /// (0x06, 0x07) which carry both coordinates along with the parity of y.
+    pub fn from_sec_allowing_hybrid(data: &[u8], curve: &Arc<CryptographicCurve>) -> Result<Point, SecError> {
+        Self::parse_sec(data, curve, true)
+    }
+
+    fn parse_sec(data: &[u8], curve: &Arc<CryptographicCurve>, allow_hybrid: bool) -> Result<Point, SecError> {
+        let prefix = *data.first().ok_or(SecError::Empty)?;
+        let finite_curve = &curve.finite_curve;
+        let prime = &finite_curve.field.prime;
+        let width = coordinate_width(&finite_curve.field);
+
+        let expected =
+            match prefix {
+                0x02 | 0x03 => 1 + width,
+                0x04 => 1 + 2 * width,
+                0x06 | 0x07 if allow_hybrid => 1 + 2 * width,
+                0x06 | 0x07 => return Err(SecError::HybridNotAllowed),
+                _ => return Err(SecError::InvalidPrefix(prefix))
+            };
+        if data.len() != expected {
+            return Err(SecError::InvalidLength { expected, actual: data.len() });
         }
 
-        // Compressed
-        let is_even = data[0] == 0x2;
-        let x = curve.finite_curve.make_element(
-            Integer::from_digits::<u8>(&data[1..], Order::MsfBe)
-        );
-        let alpha = curve.finite_curve.make_element(
-            x.clone().value.pow(3) + &curve.finite_curve.curve.b
-        );
-        let beta = alpha.sqrt();
-
-        let mut even_beta = curve.finite_curve.make_element(beta.clone());
-        let mut odd_beta = curve.finite_curve.make_element(beta.clone());
-        if beta.is_even() {
-            odd_beta = curve.finite_curve.make_element(Integer::from(&curve.finite_curve.field.prime - beta));
-        } else {
-            even_beta = curve.finite_curve.make_element(Integer::from(&curve.finite_curve.field.prime - beta.clone()));
+        let coordinate = |bytes: &[u8]| {
+            let value = Integer::from_digits::<u8>(bytes, Order::MsfBe);
+            if value < *prime {
+                Ok(finite_curve.make_element(value))
+            } else {
+                Err(SecError::CoordinateOutOfRange)
+            }
+        };
+        let x = coordinate(&data[1..1 + width])?;
+
+        if prefix == 0x02 || prefix == 0x03 {
+            let even_y = lift_x(&x, finite_curve).ok_or(SecError::NotOnCurve)?;
+            let y =
+                if prefix == 0x02 {
+                    even_y
+                } else {
+                    -even_y
+                };
+            return Ok(Point::new(Some(x), Some(y), finite_curve));
         }
 
-        if is_even {
-            Point::new(Some(x), Some(even_beta), &curve.finite_curve)
+        let y = coordinate(&data[1 + width..])?;
+        if !finite_curve.on_curve(&x, &y) {
+            return Err(SecError::NotOnCurve);
+        }
+        if prefix != 0x04 && y.is_even() != (prefix == 0x06) {
+            return Err(SecError::HybridParityMismatch);
+        }
+        Ok(Point::new(Some(x), Some(y), finite_curve))
+    }
+}
+
+/// Returns the even y coordinate of the point with the given x coordinate,
+/// or None if there is no such point on the curve.
+fn lift_x(x: &FieldElement, curve: &FiniteEllipticCurve) -> Option<FieldElement> {
+    let alpha = &(&(&(x * x) * x) + &(x * &curve.curve.a)) + &curve.curve.b;
+    let beta = Field::sqrt(&alpha)?;
+    if beta.is_even() {
+        Some(beta)
+    } else {
+        Some(-beta)
+    }
+}
+
+/// The parity of the y coordinate of a point
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    Even,
+    Odd
+}
+
+/// A BIP340 x-only public key.
+///
+/// Only the x coordinate is kept, and the y coordinate is implicitly the
+/// even one of the two points sharing that x.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XOnlyPublicKey {
+    point: Point
+}
+
+impl XOnlyPublicKey {
+    /// Create an x-only key from a full point, returning the parity of the
+    /// point's y coordinate alongside it. The key always stands for the
+    /// point with even y, which is the negation of the given point when the
+    /// parity is odd.
+    ///
+    /// Returns None for the point at infinity.
+    pub fn from_point(point: &Point) -> Option<(XOnlyPublicKey, Parity)> {
+        let (x, y) = match (point.x.as_ref(), point.y.as_ref()) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return None
+        };
+
+        if y.is_even() {
+            Some((XOnlyPublicKey { point: point.clone() }, Parity::Even))
         } else {
-            Point::new(Some(x), Some(odd_beta), &curve.finite_curve)
+            let even = Point::new(Some(x.clone()), Some(-y), &point.curve);
+            Some((XOnlyPublicKey { point: even }, Parity::Odd))
+        }
+    }
+
+    /// Parse a 32-byte x-only public key, checking that a point with that x
+    /// coordinate exists on the curve.
+    pub fn parse(data: &[u8], curve: &Arc<CryptographicCurve>) -> Result<XOnlyPublicKey, SecError> {
+        let finite_curve = &curve.finite_curve;
+        let width = coordinate_width(&finite_curve.field);
+        if data.len() != width {
+            return Err(SecError::InvalidLength { expected: width, actual: data.len() });
+        }
+
+        let value = Integer::from_digits::<u8>(data, Order::MsfBe);
+        if value >= finite_curve.field.prime {
+            return Err(SecError::CoordinateOutOfRange);
         }
+        let x = finite_curve.make_element(value);
+        let y = lift_x(&x, finite_curve).ok_or(SecError::NotOnCurve)?;
+        Ok(XOnlyPublicKey { point: Point::new(Some(x), Some(y), finite_curve) })
+    }
+
+    /// Serialize the key as its 32-byte big-endian x coordinate
+    pub fn serialize(&self) -> Vec<u8> {
+        to_fixed_width(&self.x().value, coordinate_width(&self.point.curve.field))
+    }
+
+    /// Returns the x coordinate of the key
+    pub fn x(&self) -> &FieldElement {
+        self.point.x.as_ref().unwrap()
+    }
+
+    /// Returns the point with even y that this key stands for
+    pub fn to_point(&self) -> Point {
+        self.point.clone()
     }
 }
 
 impl SignatureSerialization for Signature {
     fn as_der(&self) -> Vec<u8> {
-        // No need to strip off leading zeros because that is done for us
-        let r_bin = self.r.value.to_digits::<u8>(Order::MsfBe);
-
-        let r_bin = 
-            if r_bin[0] & 0x80 > 0 {
-                let mut value : Vec<u8> = vec![0x00];
-                value.extend(r_bin);
-                value
-            } else {
-                r_bin
-            };
+        der_encode_sequence(&[der_encode_integer(&self.r.value), der_encode_integer(&self.s.value)])
+    }
+}
 
-        let mut result : Vec<u8> = vec![0x02, r_bin.len() as u8];
-        result.extend(r_bin);
+impl Signature {
+    /// Load a DER encoded signature.
+    ///
+    /// Only the strict encoding produced by `as_der` is accepted: minimal
+    /// lengths and integers, no trailing data, and r and s in [1, n).
+    pub fn from_der(data: &[u8], curve: &Arc<CryptographicCurve>) -> Result<Signature, String> {
+        let mut outer = DerReader::new(data);
+        let mut reader = outer.read_sequence()?;
+        outer.finish()?;
+        let r = reader.read_integer()?;
+        let s = reader.read_integer()?;
+        reader.finish()?;
 
-        let s_bin = self.s.value.to_digits::<u8>(Order::MsfBe);
-        let s_bin =
-            if s_bin[0] & 0x80 > 0 {
-                let mut value : Vec<u8> = vec![0x00];
-                value.extend(s_bin);
-                value
-            } else {
-                s_bin
-            };
+        for value in &[&r, &s] {
+            if **value == 0 || **value >= curve.order.prime {
+                return Err("DER signature value is out of range".to_string());
+            }
+        }
+        Ok(Signature::new(curve.make_element(r), curve.make_element(s), curve))
+    }
+}
+
+impl PrivateKeySerialization for PrivateKey {
+    fn as_wif(&self, compressed: bool, network: Network) -> String {
+        let mut data = vec![wif_prefix(network)];
+        data.extend(to_fixed_width(&self.secret.value, 32));
+        if compressed {
+            data.push(0x01);
+        }
+        String::from_utf8(base58check_encode(data)).unwrap()
+    }
+}
+
+/// Returns the version byte of WIF private keys on the given network. Every
+/// test network shares the testnet version byte.
+fn wif_prefix(network: Network) -> u8 {
+    match network {
+        Network::Mainnet => 0x80,
+        Network::Testnet | Network::Signet | Network::Regtest => 0xef
+    }
+}
 
-        result.extend(vec![0x02, s_bin.len() as u8]);
-        result.extend(s_bin);
+impl PrivateKey {
+    /// Load a private key from Wallet Import Format.
+    ///
+    /// Returns the key along with the network it was encoded for and whether
+    /// it is meant to be used with compressed public keys. Keys for signet and
+    /// regtest cannot be told apart from testnet keys, so they are reported as
+    /// testnet.
+    pub fn from_wif(wif: &str, curve: &Arc<CryptographicCurve>) -> Result<(PrivateKey, Network, bool), String> {
+        let data = base58check_decode(wif.as_bytes())?;
+        let network =
+            match data.first() {
+                Some(&0x80) => Network::Mainnet,
+                Some(&0xef) => Network::Testnet,
+                _ => return Err("Unknown WIF version byte".to_string())
+            };
+        let compressed =
+            match data.len() {
+                33 => false,
+                34 if data[33] == 0x01 => true,
+                _ => return Err(format!("Invalid WIF length {}", data.len()))
+            };
 
-        let mut final_result : Vec<u8> = vec![0x30, result.len() as u8];
-        final_result.extend(result);
-        final_result
+        let secret = Integer::from_digits::<u8>(&data[1..33], Order::MsfBe);
+        if secret == 0 || secret >= curve.order.prime {
+            return Err("WIF secret is out of range".to_string());
+        }
+        Ok((PrivateKey::new(curve.make_element(secret), curve), network, compressed))
     }
 }
 
 #[test]
 fn test_sec_serialization() {
+    use programmingbitcoin::hex::*;
     use rug::Integer;
-    use std::rc::Rc;
+    use std::sync::Arc;
     use rug::ops::*;
 
-    let curve = Rc::new(CryptographicCurve::new_secp256k1());
+    let curve = Arc::new(CryptographicCurve::new_secp256k1());
 
     let values = vec![
         (
             Integer::from(5000),
-            b"\x04\xff\xe5X\xe3\x88\x85/\x01 \xe4j\xf2\xd1\xb3p\xf8XT\xa8\xeb\x08A\x81\x1e\xce\x0e>\x03\xd2\x82\xd5|1]\xc7(\x90\xa4\xf1\n\x14\x81\xc01\xb0;5\x1b\r\xc7\x99\x01\xca\x18\xa0\x0c\xf0\t\xdb\xdb\x15z\x1d\x10",
-            b"\x02\xff\xe5X\xe3\x88\x85/\x01 \xe4j\xf2\xd1\xb3p\xf8XT\xa8\xeb\x08A\x81\x1e\xce\x0e>\x03\xd2\x82\xd5|"
+            "04ffe558e388852f0120e46af2d1b370f85854a8eb0841811ece0e3e03d282d57c315dc72890a4f10a1481c031b03b351b0dc79901ca18a00cf009dbdb157a1d10",
+            "02ffe558e388852f0120e46af2d1b370f85854a8eb0841811ece0e3e03d282d57c"
         ),
         (
             Integer::from(2018).pow(5),
-            b"\x04\x02\x7f=\xa1\x91\x84U\xe0<F\xf6Y&j\x1b\xb5 N\x95\x9d\xb76M/G;\xdf\x8f\n\x13\xcc\x9d\xff\x87d\x7f\xd0#\xc1;JI\x94\xf1v\x91\x89X\x06\xe1\xb4\x0bW\xf4\xfd\"X\x1aOF\x85\x1f;\x06",
-            b"\x02\x02\x7f=\xa1\x91\x84U\xe0<F\xf6Y&j\x1b\xb5 N\x95\x9d\xb76M/G;\xdf\x8f\n\x13\xcc\x9d"
+            "04027f3da1918455e03c46f659266a1bb5204e959db7364d2f473bdf8f0a13cc9dff87647fd023c13b4a4994f17691895806e1b40b57f4fd22581a4f46851f3b06",
+            "02027f3da1918455e03c46f659266a1bb5204e959db7364d2f473bdf8f0a13cc9d"
         ),
         (
             Integer::from(0xdeadbeef12345i64),
-            b"\x04\xd9\x0c\xd6%\xee\x87\xdd8em\xd9\\\xf7\x9fe\xf6\x0frs\xb6}0\x96\xe6\x8b\xd8\x1eOSBi\x1f\x84.\xfav/\xd5\x99a\xd0\xe9\x98\x03\xc6\x1e\xdb\xa8\xb3\xe3\xf7\xdc:4\x186\xf9w3\xae\xbf\x98q!",
-            b"\x03\xd9\x0c\xd6%\xee\x87\xdd8em\xd9\\\xf7\x9fe\xf6\x0frs\xb6}0\x96\xe6\x8b\xd8\x1eOSBi\x1f"
+            "04d90cd625ee87dd38656dd95cf79f65f60f7273b67d3096e68bd81e4f5342691f842efa762fd59961d0e99803c61edba8b3e3f7dc3a341836f97733aebf987121",
+            "03d90cd625ee87dd38656dd95cf79f65f60f7273b67d3096e68bd81e4f5342691f"
         )
     ];
     for (secret, uncompressed, compressed) in values {
         let private_key = PrivateKey::new(curve.make_element(secret), &curve);
         let result = private_key.public_key.as_sec();
         assert_eq!(result.len(), 65);
-        assert_eq!(result, &uncompressed[..]);
+        assert_eq!(result.to_hex(), uncompressed);
 
-        let decoded = Point::from_sec(&result, &curve);
+        let decoded = Point::from_sec(&result, &curve).unwrap();
         assert_eq!(decoded, private_key.public_key);
 
         let result = private_key.public_key.as_compressed_sec();
         assert_eq!(result.len(), 33);
-        assert_eq!(result, &compressed[..]);
+        assert_eq!(result.to_hex(), compressed);
 
-        let decoded = Point::from_sec(&result, &curve);
+        let decoded = Point::from_sec(&result, &curve).unwrap();
         assert_eq!(decoded, private_key.public_key);
     }
 }
 
+#[test]
+fn test_sec_fixed_width() {
+    use rug::rand::RandState;
+
+    let curve = CryptographicCurve::secp256k1();
+    let mut rand = RandState::new();
+    let check = |public_key: &Point| {
+        let uncompressed = public_key.as_sec();
+        let compressed = public_key.as_compressed_sec();
+        assert_eq!(uncompressed.len(), 65);
+        assert_eq!(compressed.len(), 33);
+        assert_eq!(Point::from_sec(&uncompressed, &curve).unwrap(), *public_key);
+        assert_eq!(Point::from_sec(&compressed, &curve).unwrap(), *public_key);
+    };
+
+    for _ in 0..64 {
+        let secret = curve.order.prime.clone().random_below(&mut rand);
+        if secret != 0 {
+            check(&PrivateKey::new(curve.make_element(secret), &curve).public_key);
+        }
+    }
+
+    // Walk multiples of G until both a leading zero x and y byte turn up
+    let generator = &curve.generator_point;
+    let mut point = generator.clone();
+    let (mut short_x, mut short_y) = (false, false);
+    while !(short_x && short_y) {
+        let x = &point.x.as_ref().unwrap().value;
+        let y = &point.y.as_ref().unwrap().value;
+        if x.significant_bits() <= 248 || y.significant_bits() <= 248 {
+            short_x |= x.significant_bits() <= 248;
+            short_y |= y.significant_bits() <= 248;
+            check(&point);
+        }
+        point = &point + generator;
+    }
+}
+
+#[test]
+fn test_sec_validation() {
+    let curve = CryptographicCurve::secp256k1();
+    let public_key = PrivateKey::new(curve.make_element(Integer::from(5000)), &curve).public_key;
+    let uncompressed = public_key.as_sec();
+    let compressed = public_key.as_compressed_sec();
+
+    assert_eq!(Point::from_sec(&[], &curve), Err(SecError::Empty));
+
+    // The point at infinity encodes as 0x00, which is not a public key
+    let infinity = Point::infinity(&curve.finite_curve);
+    assert_eq!(infinity.as_sec(), vec![0x00]);
+    assert_eq!(infinity.as_compressed_sec(), vec![0x00]);
+    assert_eq!(Point::from_sec(&infinity.as_sec(), &curve), Err(SecError::InvalidPrefix(0x00)));
+    assert_eq!(Point::from_sec(&compressed[..20], &curve),
+               Err(SecError::InvalidLength { expected: 33, actual: 20 }));
+    assert_eq!(Point::from_sec(&uncompressed[..33], &curve),
+               Err(SecError::InvalidLength { expected: 65, actual: 33 }));
+
+    let mut bad_prefix = compressed.clone();
+    bad_prefix[0] = 0x05;
+    assert_eq!(Point::from_sec(&bad_prefix, &curve), Err(SecError::InvalidPrefix(0x05)));
+
+    // x = p is out of range, and x = 5 has no point on secp256k1
+    let mut out_of_range = vec![0x02];
+    out_of_range.extend(curve.finite_curve.field.prime.to_digits::<u8>(Order::MsfBe));
+    assert_eq!(Point::from_sec(&out_of_range, &curve), Err(SecError::CoordinateOutOfRange));
+    let mut no_root = vec![0x03; 33];
+    no_root[1..].copy_from_slice(&[0; 32]);
+    no_root[32] = 5;
+    assert_eq!(Point::from_sec(&no_root, &curve), Err(SecError::NotOnCurve));
+
+    let mut off_curve = uncompressed.clone();
+    off_curve[64] ^= 1;
+    assert_eq!(Point::from_sec(&off_curve, &curve), Err(SecError::NotOnCurve));
+
+    // Hybrid encodings are only accepted when asked for, with the right parity
+    let mut hybrid = uncompressed.clone();
+    hybrid[0] = if public_key.y.as_ref().unwrap().is_even() { 0x06 } else { 0x07 };
+    assert_eq!(Point::from_sec(&hybrid, &curve), Err(SecError::HybridNotAllowed));
+    assert_eq!(Point::from_sec_allowing_hybrid(&hybrid, &curve), Ok(public_key.clone()));
+    hybrid[0] ^= 1;
+    assert_eq!(Point::from_sec_allowing_hybrid(&hybrid, &curve), Err(SecError::HybridParityMismatch));
+    assert_eq!(Point::from_sec_allowing_hybrid(&compressed, &curve), Ok(public_key));
+}
+
+#[test]
+fn test_xonly_public_key() {
+    use rug::rand::RandState;
+
+    let curve = CryptographicCurve::secp256k1();
+
+    // From the BIP340 test vectors, secret key 3
+    let public_key = PrivateKey::new(curve.make_element(Integer::from(3)), &curve).public_key;
+    let expected = Integer::from_str_radix(
+        "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9", 16
+    ).unwrap().to_digits::<u8>(Order::MsfBe);
+    let (xonly, parity) = XOnlyPublicKey::from_point(&public_key).unwrap();
+    assert_eq!(xonly.serialize(), expected);
+    assert_eq!(XOnlyPublicKey::parse(&expected, &curve).unwrap(), xonly);
+    assert_eq!(parity == Parity::Even, public_key.y.as_ref().unwrap().is_even());
+
+    let mut rand = RandState::new();
+    let mut parities = Vec::new();
+    for _ in 0..16 {
+        let secret = curve.make_element(curve.order.prime.clone().random_below(&mut rand) + 1);
+        let public_key = PrivateKey::new(secret, &curve).public_key;
+        let (xonly, parity) = XOnlyPublicKey::from_point(&public_key).unwrap();
+        let serialized = xonly.serialize();
+        assert_eq!(serialized.len(), 32);
+        assert_eq!(XOnlyPublicKey::parse(&serialized, &curve).unwrap(), xonly);
+
+        // The key is the point itself or its negation, whichever has even y
+        let point = xonly.to_point();
+        assert!(point.y.as_ref().unwrap().is_even());
+        assert_eq!(point.x, public_key.x);
+        assert_eq!(point == public_key, parity == Parity::Even);
+        parities.push(parity);
+    }
+    assert!(parities.contains(&Parity::Even) && parities.contains(&Parity::Odd));
+
+    assert!(XOnlyPublicKey::from_point(&Point::identity(&curve.finite_curve)).is_none());
+    assert_eq!(XOnlyPublicKey::parse(&expected[1..], &curve),
+               Err(SecError::InvalidLength { expected: 32, actual: 31 }));
+    let mut no_point = vec![0; 32];
+    no_point[31] = 5;
+    assert_eq!(XOnlyPublicKey::parse(&no_point, &curve), Err(SecError::NotOnCurve));
+}
+
 #[test]
 fn test_der_serialization() {
     use rug::Integer;
-    use std::rc::Rc;
+    use std::sync::Arc;
 
-    let curve = Rc::new(CryptographicCurve::new_secp256k1());
+    let curve = Arc::new(CryptographicCurve::new_secp256k1());
     let values = vec![
         (
             Integer::from_str_radix("37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6", 16).unwrap(),
@@ -181,5 +548,50 @@ fn test_der_serialization() {
     for (r, s, sig_bytes) in values {
         let sig = Signature::new(curve.make_element(r), curve.make_element(s), &curve);
         assert_eq!(sig.as_der(), &sig_bytes[..]);
+
+        let parsed = Signature::from_der(&sig_bytes[..], &curve).unwrap();
+        assert_eq!((parsed.r, parsed.s), (sig.r, sig.s));
+
+        assert!(Signature::from_der(&sig_bytes[..sig_bytes.len() - 1], &curve).is_err());
+        let mut padded = sig_bytes.to_vec();
+        padded[1] += 1;
+        padded[3] += 1;
+        padded.insert(4, 0x00);
+        assert!(Signature::from_der(&padded, &curve).is_err());
+    }
+}
+
+#[test]
+fn test_wif_serialization() {
+    use rug::ops::*;
+
+    let curve = CryptographicCurve::secp256k1();
+    let values = vec![
+        (Integer::from(5003), true, Network::Testnet, "cMahea7zqjxrtgAbB7LSGbcQUr1uX1ojuat9jZodMN8rFTv2sfUK"),
+        (Integer::from(2021).pow(5), false, Network::Testnet, "91avARGdfge8E4tZfYLoxeJ5sGBdNJQH4kvjpWAxgzczjbCwxic"),
+        (
+            Integer::from_str_radix("54321deadbeef", 16).unwrap(),
+            true,
+            Network::Mainnet,
+            "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgiuQJv1h8Ytr2S53a"
+        )
+    ];
+
+    for (secret, compressed, network, expected) in values {
+        let private_key = PrivateKey::new(curve.make_element(secret), &curve);
+        assert_eq!(private_key.as_wif(compressed, network), expected);
+
+        let (decoded, decoded_network, decoded_compressed) = PrivateKey::from_wif(expected, &curve).unwrap();
+        assert_eq!(decoded.secret, private_key.secret);
+        assert_eq!((decoded_network, decoded_compressed), (network, compressed));
     }
+
+    // Signet keys use the testnet version byte and parse back as testnet
+    let private_key = PrivateKey::new(curve.make_element(Integer::from(5003)), &curve);
+    let wif = private_key.as_wif(true, Network::Signet);
+    assert_eq!(wif, private_key.as_wif(true, Network::Testnet));
+    let (decoded, decoded_network, _) = PrivateKey::from_wif(&wif, &curve).unwrap();
+    assert_eq!((decoded.secret, decoded_network), (private_key.secret, Network::Testnet));
+
+    assert!(PrivateKey::from_wif("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgiuQJv1h8Ytr2S53b", &curve).is_err());
 }